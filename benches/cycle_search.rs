@@ -0,0 +1,101 @@
+//! Compares the incremental cycle search with the search it replaced, which scanned the whole history after every entry.
+//!
+//! The baseline only looks for the smallest cycle and stops at the first one, hence it is fast on histories ending in a short cycle, but
+//! scans the whole history when there is none. The incremental search reports every cycle size up to `MAX_CYCLE_SIZE`, at a cost
+//! depending on how often the new entry occurs among the last `MAX_CYCLE_SIZE` entries, but not on the length of the history.
+//!
+//! Run with `cargo bench --bench cycle_search`.
+
+// the crate is a binary only, the module is included directly; its test helpers are unused here
+#[path = "../src/cycle_search.rs"]
+#[allow(dead_code)]
+mod cycle_search;
+
+#[path = "../src/history_generators.rs"]
+mod history_generators;
+
+use cycle_search::CycleSearch;
+use history_generators::{generate_nested_history, generate_random_history};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const HISTORY_LENGTH: usize = 1_000_000;
+// the baseline is only timed on the last entries of the history, searching all of them takes hours
+const BASELINE_SAMPLES: usize = 200;
+const MAX_CYCLE_SIZE: usize = 1000;
+
+/// the search before `CycleSearch`: the smallest cycle ending at the head of the history, scanning backwards from the head
+fn find_smallest_cycle_size(history: &[(String, u32)]) -> Option<usize> {
+    let history_size = history.len();
+    let head_index = history_size - 1;
+    let head = &history[head_index];
+
+    if history_size < 2 {
+        return None;
+    }
+
+    let mut candidate_index = head_index - 1;
+    loop {
+        if history[candidate_index] == *head {
+            let expected_cycle_size = head_index - candidate_index;
+            let mut head_check = head_index - 1;
+            while head_check > candidate_index && head_check >= expected_cycle_size {
+                if history[head_check - expected_cycle_size] != history[head_check] {
+                    break;
+                }
+                head_check -= 1;
+            }
+
+            if head_check == candidate_index {
+                return Some(expected_cycle_size);
+            }
+        }
+
+        if candidate_index == 0 {
+            break;
+        }
+        candidate_index -= 1;
+    }
+
+    None
+}
+
+/// time per entry of pushing the whole history
+fn bench_incremental(history: &[(String, u32)]) -> Duration {
+    let mut cycle_search = CycleSearch::new(MAX_CYCLE_SIZE);
+    let start = Instant::now();
+    for entry in history.iter() {
+        black_box(cycle_search.push(entry.clone()));
+    }
+    assert_eq!(cycle_search.len(), history.len());
+
+    start.elapsed() / history.len() as u32
+}
+
+/// time per entry of searching the history after each of its last entries
+fn bench_baseline(history: &[(String, u32)]) -> Duration {
+    let start = Instant::now();
+    for head_index in history.len() - BASELINE_SAMPLES..history.len() {
+        black_box(find_smallest_cycle_size(&history[..=head_index]));
+    }
+
+    start.elapsed() / BASELINE_SAMPLES as u32
+}
+
+fn main() {
+    let histories = [
+        ("random, 3 facts", generate_random_history(HISTORY_LENGTH, 3)),
+        ("random, 1000 facts", generate_random_history(HISTORY_LENGTH, 1000)),
+        ("random, 100000 facts", generate_random_history(HISTORY_LENGTH, 100_000)),
+        ("nested cycles", generate_nested_history(HISTORY_LENGTH)),
+    ];
+
+    println!("{HISTORY_LENGTH} entries, cycles up to size {MAX_CYCLE_SIZE}");
+    println!("{:<22} {:>14} {:>14} {:>10}", "history", "incremental", "baseline", "speedup");
+    for (name, history) in histories.iter() {
+        let incremental = bench_incremental(history);
+        let baseline = bench_baseline(history);
+        let speedup = baseline.as_secs_f64() / incremental.as_secs_f64().max(f64::EPSILON);
+        println!("{name:<22} {:>11.2?}/op {:>11.2?}/op {speedup:>9.2}x", incremental, baseline);
+    }
+}
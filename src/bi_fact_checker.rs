@@ -0,0 +1,72 @@
+use crate::iteration_summary::IterationSummary;
+use crate::saturation_state::Iteration;
+use crate::term::{Side, TermMetrics};
+use crate::witness::LoopSpan;
+use std::collections::HashMap;
+
+// number of new maxima of one side, without the other side growing, before warning
+const ONE_SIDED_GROWTH_STREAK: usize = 3;
+
+/// warns when the bi-facts of only one side of the biprocess keep growing, as the terms of both sides usually grow together
+pub struct BiFactChecker {
+    maximum_sizes: HashMap<String, [usize; 2]>,
+    streaks: HashMap<String, (Side, usize)>,
+}
+
+impl BiFactChecker {
+    pub fn new() -> Self {
+        BiFactChecker { maximum_sizes: HashMap::new(), streaks: HashMap::new() }
+    }
+
+    pub fn check(&mut self, iteration: &Iteration, printer: &mut IterationSummary) {
+        let Some(fact) = iteration.selected_fact().and_then(|f| f.term.as_ref()) else {
+            return;
+        };
+        if !fact.is_bi_fact() {
+            return;
+        }
+
+        let predicate = fact.predicate().unwrap_or("").to_string();
+        let sizes = [Side::Left, Side::Right].map(|side| TermMetrics::of_fact(&fact.project(side)).size);
+        let maximum_sizes = self.maximum_sizes.entry(predicate.clone()).or_default();
+        let grown = [sizes[0] > maximum_sizes[0], sizes[1] > maximum_sizes[1]];
+        maximum_sizes[0] = maximum_sizes[0].max(sizes[0]);
+        maximum_sizes[1] = maximum_sizes[1].max(sizes[1]);
+
+        let (side, size, other_size) = match grown {
+            [true, false] => (Side::Left, sizes[0], sizes[1]),
+            [false, true] => (Side::Right, sizes[1], sizes[0]),
+            [true, true] => {
+                self.streaks.remove(&predicate);
+                return;
+            }
+            [false, false] => return,
+        };
+
+        let streak = self.streaks.entry(predicate.clone()).or_insert((side, 0));
+        if streak.0 != side {
+            *streak = (side, 0);
+        }
+        streak.1 += 1;
+
+        if streak.1 >= ONE_SIDED_GROWTH_STREAK {
+            let description = format!("only the {side} side of {predicate} grows: size {size} (other side {other_size}), {} times in a row", streak.1);
+            printer.add_warning("BiFact".to_string(), description.clone());
+            printer.add_loop("BiFact".to_string(), description, LoopSpan::Ancestry);
+        }
+    }
+}
+
+#[test]
+fn test_one_sided_growth() {
+    let mut checker = BiFactChecker::new();
+    let mut warnings = vec![];
+    for fact in ["att2(a,a)", "att2(f(a),f(a))", "att2(f(f(a)),a)", "att2(f(f(f(a))),a)", "att2(f(f(f(f(a)))),b)"] {
+        let iteration = Iteration::for_test(1, "", Some(fact));
+        let mut summary = IterationSummary::for_test();
+        checker.check(&iteration, &mut summary);
+        warnings.extend(summary.findings().iter().map(|(_, _, line)| line.to_string()));
+    }
+
+    assert_eq!(warnings, vec!["only the left side of att2 grows: size 5 (other side 1), 3 times in a row"]);
+}
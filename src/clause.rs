@@ -0,0 +1,154 @@
+use crate::term::{Side, Term};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    pub hypotheses: Vec<Term>,
+    pub conclusion: Term,
+}
+
+impl Clause {
+    /// parses clauses of the form `H1 && H2 -> C` (or only `C` if there are no hypotheses)
+    pub fn parse(input: &str) -> Option<Clause> {
+        let parts = split_top_level(input, "->");
+        let (hypotheses, conclusion) = match parts.as_slice() {
+            [conclusion] => (vec![], *conclusion),
+            [hypotheses, conclusion] => (split_top_level(hypotheses, "&&"), *conclusion),
+            _ => return None,
+        };
+
+        let hypotheses = hypotheses.iter().map(|hypothesis| Term::parse(hypothesis)).collect::<Option<Vec<Term>>>()?;
+        let conclusion = Term::parse(conclusion)?;
+
+        Some(Clause { hypotheses, conclusion })
+    }
+
+    /// sorts the hypotheses by their shape (the terms without variables) and renames variables in order of their occurrence,
+    /// hence clauses equal up to renaming and order of the hypotheses usually have the same canonical form
+    pub fn canonical(&self) -> Clause {
+        let mut hypotheses: Vec<&Term> = self.hypotheses.iter().collect();
+        hypotheses.sort_by_cached_key(|hypothesis| hypothesis.erase_variables().to_string());
+
+        let mut renaming = HashMap::new();
+        let hypotheses = hypotheses.iter().map(|hypothesis| hypothesis.rename_variables(&mut renaming)).collect();
+        let conclusion = self.conclusion.rename_variables(&mut renaming);
+
+        Clause { hypotheses, conclusion }
+    }
+
+    pub fn project(&self, side: Side) -> Clause {
+        Clause {
+            hypotheses: self.hypotheses.iter().map(|hypothesis| hypothesis.project(side)).collect(),
+            conclusion: self.conclusion.project(side),
+        }
+    }
+}
+
+/// origin of an initial clause, guessed from its predicates
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ClauseKind {
+    /// capabilities of the attacker: only attacker facts, constraints and `bad`
+    Attacker,
+    /// generated from the processes, e.g. involving messages, tables or events
+    Process,
+    /// concluding an equality, generated from the equational theory
+    Equational,
+}
+
+impl Clause {
+    pub fn kind(&self) -> ClauseKind {
+        let predicate = self.conclusion.predicate().unwrap_or("");
+        if predicate.starts_with("equal") {
+            return ClauseKind::Equational;
+        }
+
+        let is_attacker = |term: &Term| term.predicate().is_some_and(|predicate| predicate.starts_with("att") || predicate == "bad" || !predicate.starts_with(char::is_alphabetic));
+        if is_attacker(&self.conclusion) && self.hypotheses.iter().all(is_attacker) {
+            return ClauseKind::Attacker;
+        }
+
+        ClauseKind::Process
+    }
+}
+
+impl Display for ClauseKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClauseKind::Attacker => write!(f, "attacker"),
+            ClauseKind::Process => write!(f, "process"),
+            ClauseKind::Equational => write!(f, "equational"),
+        }
+    }
+}
+
+impl Display for Clause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.hypotheses.is_empty() {
+            return write!(f, "{}", self.conclusion);
+        }
+
+        let hypotheses: Vec<String> = self.hypotheses.iter().map(|hypothesis| hypothesis.to_string()).collect();
+        write!(f, "{} -> {}", hypotheses.join(" && "), self.conclusion)
+    }
+}
+
+/// canonical form of the clause if it can be parsed, else the clause as is
+pub fn canonicalize(clause: &str) -> String {
+    match Clause::parse(clause) {
+        Some(clause) => clause.canonical().to_string(),
+        None => clause.to_string(),
+    }
+}
+
+fn split_top_level<'a>(input: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut part_start = 0;
+
+    for (index, char) in input.char_indices() {
+        match char {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ if depth == 0 && input[index..].starts_with(separator) => {
+                parts.push(input[part_start..index].trim());
+                part_start = index + separator.len();
+            }
+            _ => {}
+        }
+    }
+    parts.push(input[part_start..].trim());
+
+    parts
+}
+
+#[test]
+fn test_parse_clause() {
+    let clause = Clause::parse("mess2(c[],f(x_1,(y_2,z_3))) && att2(x_1,y_2) -> att2(f(x_1,y_2),z_3)").unwrap();
+    assert_eq!(clause.hypotheses.len(), 2);
+    assert_eq!(clause.to_string(), "mess2(c[],f(x_1,(y_2,z_3))) && att2(x_1,y_2) -> att2(f(x_1,y_2),z_3)");
+
+    assert_eq!(Clause::parse("att2(x_1,y_1)").unwrap().hypotheses.len(), 0);
+    assert_eq!(Clause::parse("att2(x_1 -> y_1"), None);
+}
+
+#[test]
+fn test_canonicalize() {
+    let canonical = canonicalize("att2(v_12,@1_j_3) && mess2(c[],v_12) -> att2(f(v_12),@1_j_3)");
+    assert_eq!(canonical, "att2(x_1,x_2) && mess2(c[],x_1) -> att2(f(x_1),x_2)");
+    assert_eq!(canonicalize("att2(v_3,@1_j_7) && mess2(c[],v_3) -> att2(f(v_3),@1_j_7)"), canonical);
+    assert_eq!(canonicalize("mess2(c[],v_3) && att2(v_3,@1_j_7) -> att2(f(v_3),@1_j_7)"), canonical);
+
+    let canonical = canonicalize("att2(y_1,y_1) && y_1 >= 2 -> att2(f(y_1),y_1)");
+    assert_eq!(canonical, "x_1 >= 2 && att2(x_1,x_1) -> att2(f(x_1),x_1)");
+    assert_eq!(canonicalize("att2(v_4,v_4) && v_4 >= 2 -> att2(f(v_4),v_4)"), canonical);
+}
+
+#[test]
+fn test_clause_kind() {
+    let kind = |clause: &str| Clause::parse(clause).unwrap().kind();
+    assert_eq!(kind("att2(x_1,y_1) && att2(x_2,y_2) -> att2(enc(x_1,x_2),enc(y_1,y_2))"), ClauseKind::Attacker);
+    assert_eq!(kind("att2(x_1,y_1) && att2(x_1,y_2) && y_1 <> y_2 -> bad"), ClauseKind::Attacker);
+    assert_eq!(kind("mess2(c[],x_1) -> att2(x_1,x_1)"), ClauseKind::Process);
+    assert_eq!(kind("equal2(f(x_1),g(x_1))"), ClauseKind::Equational);
+}
@@ -0,0 +1,227 @@
+use crate::clause::{canonicalize, Clause};
+use crate::saturation_state::QueueEntry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// a clause identified by its ProVerif rule number, from entering the queue to being selected (and added to the base)
+#[derive(Clone)]
+pub struct TrackedClause {
+    pub entry: QueueEntry,
+    /// predicate of the conclusion, `?` if the clause could not be parsed
+    pub predicate: String,
+    /// how often a clause equal to this one up to renaming was queued, including this time
+    pub queued_count: usize,
+    pub enqueued_iteration: usize,
+    /// derivation depth of the clause, one more than the depth of the iteration which queued it
+    pub depth: Option<usize>,
+    pub selected_iteration: Option<usize>,
+    /// left the queue without being selected, e.g. because it was subsumed
+    pub dropped_iteration: Option<usize>,
+}
+
+impl TrackedClause {
+    /// zero if the iteration numbers went down while the clause was queued
+    pub fn waiting_time(&self) -> Option<usize> {
+        Some(self.selected_iteration?.saturating_sub(self.enqueued_iteration))
+    }
+}
+
+/// queue statistics of the clauses concluding one predicate
+#[derive(Debug, Default, PartialEq)]
+pub struct PredicateQueueStatistics {
+    pub queued: usize,
+    pub selected: usize,
+    pub still_queued: usize,
+    /// average number of iterations between enqueue and selection
+    pub average_waiting_time: f64,
+    /// average number of iterations the clauses still in the queue have been waiting so far
+    pub average_age: f64,
+}
+
+/// follows the queued clauses through the queue dumps, to know which clause each iteration selected
+pub struct ClauseTracker {
+    pub clauses: BTreeMap<usize, TrackedClause>,
+    queued: BTreeSet<usize>,
+    queued_counts: HashMap<String, usize>,
+    /// number of queued clauses per predicate
+    pub backlog: BTreeMap<String, usize>,
+    /// selections of another clause than the head of the queue, i.e. the queue did not behave FIFO
+    pub out_of_order_selections: usize,
+    /// the head of the queue, if the last selection passed it over
+    pub skipped_head: Option<usize>,
+    last_iteration: usize,
+}
+
+impl ClauseTracker {
+    pub fn new() -> Self {
+        ClauseTracker {
+            clauses: BTreeMap::new(),
+            queued: BTreeSet::new(),
+            queued_counts: HashMap::new(),
+            backlog: BTreeMap::new(),
+            out_of_order_selections: 0,
+            skipped_head: None,
+            last_iteration: 0,
+        }
+    }
+
+    /// finds the queued clause the iteration selected: the oldest one equal to the query up to renaming, else the head of the queue
+    pub fn select(&mut self, query: &str, iteration: usize) -> Option<usize> {
+        let canonical_query = canonicalize(query);
+        let number = self.queued.iter().find(|number| self.clauses[number].entry.canonical == canonical_query).or(self.queued.first()).copied()?;
+
+        let head = *self.queued.first()?;
+        self.skipped_head = (head != number).then_some(head);
+        if self.skipped_head.is_some() {
+            self.out_of_order_selections += 1;
+        }
+
+        self.queued.remove(&number);
+        self.last_iteration = iteration;
+        let clause = self.clauses.get_mut(&number)?;
+        clause.selected_iteration = Some(iteration);
+        decrement(&mut self.backlog, &clause.predicate);
+        Some(number)
+    }
+
+    /// updates the queue with a complete dump, and returns the clauses which were not queued before
+    pub fn update_queue(&mut self, dump: &[QueueEntry], iteration: usize, depth_of: impl Fn(&QueueEntry) -> Option<usize>) -> Vec<QueueEntry> {
+        let dumped: BTreeSet<usize> = dump.iter().map(|entry| entry.number).collect();
+        for number in self.queued.difference(&dumped) {
+            if let Some(clause) = self.clauses.get_mut(number) {
+                clause.dropped_iteration = Some(iteration);
+                decrement(&mut self.backlog, &clause.predicate);
+            }
+        }
+        self.queued = dumped;
+        self.last_iteration = iteration;
+
+        let mut new_entries = Vec::new();
+        for entry in dump {
+            if self.clauses.contains_key(&entry.number) {
+                continue;
+            }
+
+            let predicate = Clause::parse(&entry.rule).and_then(|clause| clause.conclusion.predicate().map(str::to_string)).unwrap_or("?".to_string());
+            let queued_count = self.queued_counts.entry(entry.canonical.clone()).or_default();
+            *queued_count += 1;
+            *self.backlog.entry(predicate.clone()).or_default() += 1;
+
+            let clause = TrackedClause {
+                entry: entry.clone(),
+                predicate,
+                queued_count: *queued_count,
+                enqueued_iteration: iteration,
+                depth: depth_of(entry),
+                selected_iteration: None,
+                dropped_iteration: None,
+            };
+            self.clauses.insert(entry.number, clause);
+            new_entries.push(entry.clone());
+        }
+
+        new_entries
+    }
+
+    pub fn queued(&self) -> impl Iterator<Item = &TrackedClause> {
+        self.queued.iter().map(|number| &self.clauses[number])
+    }
+
+    pub fn never_selected(&self) -> impl Iterator<Item = &TrackedClause> {
+        self.clauses.values().filter(|clause| clause.selected_iteration.is_none())
+    }
+
+    pub fn predicate_statistics(&self) -> BTreeMap<&str, PredicateQueueStatistics> {
+        let mut statistics: BTreeMap<&str, PredicateQueueStatistics> = BTreeMap::new();
+        let mut total_waiting_times: HashMap<&str, usize> = HashMap::new();
+        let mut total_ages: HashMap<&str, usize> = HashMap::new();
+        for clause in self.clauses.values() {
+            let predicate_statistics = statistics.entry(&clause.predicate).or_default();
+            predicate_statistics.queued += 1;
+            if let Some(waiting_time) = clause.waiting_time() {
+                predicate_statistics.selected += 1;
+                *total_waiting_times.entry(&clause.predicate).or_default() += waiting_time;
+            }
+        }
+        for clause in self.queued() {
+            statistics.get_mut(clause.predicate.as_str()).unwrap().still_queued += 1;
+            *total_ages.entry(&clause.predicate).or_default() += self.last_iteration.saturating_sub(clause.enqueued_iteration);
+        }
+
+        for (predicate, predicate_statistics) in statistics.iter_mut() {
+            predicate_statistics.average_waiting_time = total_waiting_times.get(predicate).copied().unwrap_or(0) as f64 / predicate_statistics.selected.max(1) as f64;
+            predicate_statistics.average_age = total_ages.get(predicate).copied().unwrap_or(0) as f64 / predicate_statistics.still_queued.max(1) as f64;
+        }
+        statistics
+    }
+}
+
+fn decrement(backlog: &mut BTreeMap<String, usize>, predicate: &str) {
+    if let Some(count) = backlog.get_mut(predicate) {
+        *count = count.saturating_sub(1);
+    }
+}
+
+#[test]
+fn test_clause_tracker() {
+    let entry = |number: usize, rule: &str| QueueEntry::new(number, rule.to_string());
+    let mut tracker = ClauseTracker::new();
+
+    let new_entries = tracker.update_queue(&[entry(1, "att2(x_1,y_1) -> att2(f(x_1),y_1)"), entry(2, "att2(a[],a[])")], 1, |_| None);
+    assert_eq!(new_entries.len(), 2);
+
+    // the query matches the second entry up to renaming, even though the first entry is the head of the queue
+    assert_eq!(tracker.select("att2(a[],a[])", 2), Some(2));
+    let new_entries = tracker.update_queue(&[entry(3, "att2(b[],b[])")], 2, |_| None);
+    assert_eq!(new_entries.iter().map(|entry| entry.number).collect::<Vec<_>>(), vec![3]);
+
+    // without a match, the head of the queue is selected
+    assert_eq!(tracker.select("att2(v_1,v_2) -> att2(g(v_1),v_2)", 5), Some(3));
+
+    assert_eq!(tracker.clauses[&2].waiting_time(), Some(1));
+    assert_eq!(tracker.clauses[&1].dropped_iteration, Some(2));
+    assert_eq!(tracker.never_selected().map(|clause| clause.entry.number).collect::<Vec<_>>(), vec![1]);
+
+    // the first selection passed over the head of the queue
+    assert_eq!(tracker.out_of_order_selections, 1);
+    assert_eq!(tracker.skipped_head, None);
+}
+
+#[test]
+fn test_queue_statistics() {
+    let entry = |number: usize, rule: &str| QueueEntry::new(number, rule.to_string());
+    let mut tracker = ClauseTracker::new();
+
+    tracker.update_queue(&[entry(1, "att2(a[],a[])"), entry(2, "mess2(c[],a[])"), entry(3, "mess2(c[],b[])")], 1, |_| None);
+    tracker.select("att2(a[],a[])", 3);
+    tracker.update_queue(&[entry(2, "mess2(c[],a[])"), entry(3, "mess2(c[],b[])"), entry(4, "att2(a[],a[])")], 3, |_| None);
+    tracker.select("mess2(c[],a[])", 5);
+    tracker.update_queue(&[entry(3, "mess2(c[],b[])"), entry(4, "att2(a[],a[])")], 5, |_| None);
+
+    // the clause selected first was queued again
+    assert_eq!(tracker.clauses[&4].queued_count, 2);
+    assert_eq!(tracker.backlog["mess2"], 1);
+    assert_eq!(tracker.backlog["att2"], 1);
+    assert_eq!(tracker.out_of_order_selections, 0);
+
+    let statistics = tracker.predicate_statistics();
+    assert_eq!(
+        statistics["mess2"],
+        PredicateQueueStatistics {
+            queued: 2,
+            selected: 1,
+            still_queued: 1,
+            average_waiting_time: 4.0,
+            average_age: 4.0
+        }
+    );
+    assert_eq!(
+        statistics["att2"],
+        PredicateQueueStatistics {
+            queued: 2,
+            selected: 1,
+            still_queued: 1,
+            average_waiting_time: 2.0,
+            average_age: 2.0
+        }
+    );
+}
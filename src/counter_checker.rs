@@ -0,0 +1,115 @@
+use crate::iteration_summary::IterationSummary;
+use crate::saturation_state::Iteration;
+use crate::term::Term;
+use std::collections::HashMap;
+
+// increases in a row before warning, reported again whenever the number of increases doubled
+const COUNTER_STREAK: usize = 3;
+
+struct Counter {
+    value: u64,
+    // iteration where the value was seen last
+    iteration: usize,
+    // value and iteration where the increases in a row started
+    start: (u64, usize),
+    increases: usize,
+    reported_increases: usize,
+}
+
+impl Counter {
+    fn new(value: u64, iteration: usize) -> Self {
+        Counter {
+            value,
+            iteration,
+            start: (value, iteration),
+            increases: 0,
+            reported_increases: 0,
+        }
+    }
+}
+
+/// follows the integer literals of the selected facts by position, and warns when one keeps increasing (e.g. 5, 6, 7, ...),
+/// long before it has two digits
+pub struct CounterChecker {
+    counters: HashMap<String, Counter>,
+}
+
+impl CounterChecker {
+    pub fn new() -> Self {
+        CounterChecker { counters: HashMap::new() }
+    }
+
+    pub fn check(&mut self, iteration: &Iteration, printer: &mut IterationSummary) {
+        let Some(fact) = iteration.selected_fact().and_then(|f| f.term.as_ref()) else {
+            return;
+        };
+        let iteration_number = iteration.progress.iteration;
+
+        let mut integers = Vec::new();
+        collect_integers(fact, fact.predicate().unwrap_or("?").to_string(), &mut integers);
+        for (path, value) in integers {
+            let counter = self.counters.entry(path.clone()).or_insert(Counter::new(value, iteration_number));
+            // iteration numbers which do not increase (e.g. a log without query headers) start over as well
+            if value < counter.value || iteration_number <= counter.iteration {
+                *counter = Counter::new(value, iteration_number);
+                continue;
+            }
+            counter.iteration = iteration_number;
+            if value == counter.value {
+                continue;
+            }
+
+            counter.value = value;
+            counter.increases += 1;
+            if counter.increases >= COUNTER_STREAK && counter.increases >= 2 * counter.reported_increases {
+                counter.reported_increases = counter.increases;
+                let (start_value, start_iteration) = counter.start;
+                let rate = (iteration_number - start_iteration) as f64 / (value - start_value) as f64;
+                printer.add_warning("Counter".to_string(), format!("{path} increased {} times in a row, from {start_value} to {value} (by 1 every {rate:.1} iterations)", counter.increases));
+            }
+        }
+    }
+}
+
+/// integer literals with their position, e.g. `mess2.3.1` for the second element of the fourth argument of `mess2`
+pub fn collect_integers(term: &Term, path: String, integers: &mut Vec<(String, u64)>) {
+    match term {
+        Term::Integer(value) => integers.push((path, *value)),
+        Term::Function(_, arguments) | Term::Tuple(arguments) => arguments.iter().enumerate().for_each(|(index, argument)| collect_integers(argument, format!("{path}.{index}"), integers)),
+        Term::Name(_, annotations) => annotations.iter().for_each(|(key, value)| collect_integers(value, format!("{path}[{key}]"), integers)),
+        Term::Choice(left, right) => {
+            collect_integers(left, format!("{path}.left"), integers);
+            collect_integers(right, format!("{path}.right"), integers);
+        }
+        Term::Variable(_) => {}
+    }
+}
+
+#[test]
+fn test_counter_checker() {
+    let mut checker = CounterChecker::new();
+    let mut warnings = vec![];
+    let facts = ["mess2(c[],(5,a))", "mess2(c[],(6,a))", "mess2(c[],(6,b))", "mess2(c[],(7,a))", "mess2(c[],(8,a))", "mess2(c[],(3,a))", "mess2(c[],(4,a))"];
+    for (index, fact) in facts.iter().enumerate() {
+        let iteration = Iteration::for_test(2 * index, "", Some(fact));
+        let mut summary = IterationSummary::for_test();
+        checker.check(&iteration, &mut summary);
+        warnings.extend(summary.findings().iter().map(|(_, _, line)| line.to_string()));
+    }
+
+    assert_eq!(warnings, vec!["mess2.1.0 increased 3 times in a row, from 5 to 8 (by 1 every 2.7 iterations)"]);
+}
+
+#[test]
+fn test_counter_checker_decreasing_iterations() {
+    let mut checker = CounterChecker::new();
+    let mut warnings = vec![];
+    for (index, iteration_number) in [5, 4, 3, 2, 1, 2, 3, 4].into_iter().enumerate() {
+        let iteration = Iteration::for_test(iteration_number, "", Some(&format!("mess2(c[],{index})")));
+        let mut summary = IterationSummary::for_test();
+        checker.check(&iteration, &mut summary);
+        warnings.extend(summary.findings().iter().map(|(_, _, line)| line.to_string()));
+    }
+
+    assert_eq!(warnings, vec!["mess2.1 increased 3 times in a row, from 4 to 7 (by 1 every 1.0 iterations)"]);
+}
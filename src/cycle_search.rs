@@ -0,0 +1,179 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Incrementally finds the cycles at the end of a growing history.
+///
+/// For every candidate cycle size `p` the search keeps the length of the current streak of entries which equal the entry `p` positions earlier.
+/// A cycle of size `p` repeats at least twice iff this streak is at least `p` long.
+/// New candidates only arise from earlier occurrences of the new entry, hence each step costs O(s + o), with `s` the number of open streaks
+/// and `o` the occurrences of the new entry within the last `max_cycle_size` entries. Both are at most `max_cycle_size`, and usually much
+/// smaller, but do not depend on the length of the history: the search is linear in the length of the history where searching the whole
+/// history after every entry is quadratic (see `benches/cycle_search.rs`).
+///
+/// Only the last `max_cycle_size` entries are kept, entries which left this window are forgotten.
+pub struct CycleSearch<T> {
+    max_cycle_size: usize,
+    pushed: usize,
+
+    interned: HashMap<T, u32>,
+    next_id: u32,
+    // the last `max_cycle_size + 1` entries, the newest at the back
+    window: VecDeque<(u32, T)>,
+    occurrences: HashMap<u32, VecDeque<usize>>,
+    streaks: HashMap<usize, usize>,
+}
+
+/// a cycle which repeats at least twice at the end of the history
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CycleCandidate {
+    pub size: usize,
+    // first history entry of the periodic region
+    pub start: usize,
+}
+
+impl<T: Eq + Hash + Clone> CycleSearch<T> {
+    pub fn new(max_cycle_size: usize) -> Self {
+        CycleSearch {
+            max_cycle_size,
+            pushed: 0,
+
+            interned: HashMap::new(),
+            next_id: 0,
+            window: VecDeque::new(),
+            occurrences: HashMap::new(),
+            streaks: HashMap::new(),
+        }
+    }
+
+    /// number of entries pushed so far
+    pub fn len(&self) -> usize {
+        self.pushed
+    }
+
+    /// appends the entry, and returns all cycles which repeat at least twice at the end of the history (ordered by size)
+    pub fn push(&mut self, entry: T) -> Vec<CycleCandidate> {
+        let id = match self.interned.get(&entry) {
+            Some(id) => *id,
+            None => {
+                self.next_id = self.next_id.wrapping_add(1);
+                self.interned.insert(entry.clone(), self.next_id);
+                self.next_id
+            }
+        };
+
+        let head_index = self.pushed;
+        self.pushed += 1;
+        self.window.push_back((id, entry));
+        if self.window.len() > self.max_cycle_size + 1 {
+            self.forget_oldest_entry();
+        }
+
+        // extend or break open streaks
+        let window = &self.window;
+        self.streaks.retain(|size, streak| {
+            if window[window.len() - 1 - size].0 != id {
+                return false;
+            }
+
+            *streak += 1;
+            true
+        });
+
+        // open streaks for earlier occurrences within the window
+        let occurrences = self.occurrences.entry(id).or_default();
+        for index in occurrences.iter() {
+            self.streaks.entry(head_index - index).or_insert(1);
+        }
+        occurrences.push_back(head_index);
+
+        let mut candidates: Vec<CycleCandidate> = self.streaks.iter().filter(|(size, streak)| *streak >= *size).map(|(size, streak)| CycleCandidate { size: *size, start: head_index + 1 - streak - size }).collect();
+        candidates.sort_by_key(|candidate| candidate.size);
+
+        candidates
+    }
+
+    fn forget_oldest_entry(&mut self) {
+        let Some((id, entry)) = self.window.pop_front() else {
+            return;
+        };
+
+        let Some(occurrences) = self.occurrences.get_mut(&id) else {
+            return;
+        };
+        occurrences.pop_front();
+        if occurrences.is_empty() {
+            self.occurrences.remove(&id);
+            self.interned.remove(&entry);
+        }
+    }
+}
+
+/// reference implementation: searches the whole history again after every entry
+#[cfg(test)]
+fn find_cycle_candidates<T: Eq>(history: &[T]) -> Vec<CycleCandidate> {
+    let history_size = history.len();
+    let head_index = history_size - 1;
+    let head = &history[head_index];
+
+    let mut candidates = Vec::new();
+    for size in 1..=history_size / 2 {
+        if history[head_index - size] != *head {
+            continue;
+        }
+
+        let window_start = history_size - 2 * size;
+        let is_cycle = (window_start..head_index - size).all(|index| history[index] == history[index + size]);
+        if is_cycle {
+            let mut start = window_start;
+            while start > 0 && history[start - 1] == history[start - 1 + size] {
+                start -= 1;
+            }
+
+            candidates.push(CycleCandidate { size, start });
+        }
+    }
+
+    candidates
+}
+
+#[test]
+fn test_cycle_search_matches_reference() {
+    use crate::history_generators::{generate_nested_history, generate_random_history};
+
+    let histories = vec![generate_random_history(1000, 3), generate_random_history(1000, 8), generate_nested_history(1000)];
+
+    for history in histories {
+        let mut cycle_search = CycleSearch::new(history.len());
+        for index in 0..history.len() {
+            let candidates = cycle_search.push(history[index].clone());
+            assert_eq!(candidates, find_cycle_candidates(&history[..=index]), "mismatch at entry {index}");
+        }
+    }
+}
+
+#[test]
+fn test_cycle_search_respects_max_cycle_size() {
+    use crate::history_generators::generate_nested_history;
+
+    let history = generate_nested_history(100);
+
+    let mut cycle_search = CycleSearch::new(5);
+    let sizes: Vec<usize> = history.into_iter().flat_map(|entry| cycle_search.push(entry)).map(|candidate| candidate.size).collect();
+    assert!(sizes.contains(&2));
+    assert!(!sizes.contains(&12));
+}
+
+#[test]
+fn test_cycle_search_forgets_old_entries() {
+    use crate::history_generators::generate_random_history;
+
+    let mut cycle_search = CycleSearch::new(4);
+    for entry in generate_random_history(1000, 50) {
+        cycle_search.push(entry);
+    }
+
+    assert_eq!(cycle_search.len(), 1000);
+    assert_eq!(cycle_search.window.len(), 5);
+    assert!(cycle_search.interned.len() <= 5);
+    assert!(cycle_search.occurrences.len() <= 5);
+}
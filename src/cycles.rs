@@ -1,5 +1,26 @@
-use crate::iteration_summary::IterationSummary;
-use std::fmt::Debug;
+use crate::clause::canonicalize;
+use crate::cycle_search::CycleSearch;
+use crate::iteration_summary::{IterationSummary, Severity};
+use crate::report_policy::ThresholdPolicy;
+use crate::saturation_state::Iteration;
+use crate::witness::LoopSpan;
+use crate::Cli;
+use clap::ValueEnum;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display, Formatter};
+
+/// the part of an iteration in which cycles are searched
+#[derive(Debug, PartialEq, Eq, Copy, Clone, ValueEnum)]
+pub enum CycleSignal {
+    /// selected hypothesis facts
+    Hypothesis,
+    /// selected conclusion facts
+    Conclusion,
+    /// whole queries, up to renaming of variables
+    Query,
+    /// predicate of the selected fact
+    Predicate,
+}
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Cycle {
@@ -7,137 +28,220 @@ pub struct Cycle {
     pub repeat: usize,
 }
 
-pub struct CycleDetector {
-    last_cycle: Option<Cycle>,
-    last_cycle_end: usize,
+#[derive(Debug, Copy, Clone)]
+struct ActiveCycle {
+    cycle: Cycle,
+    // first history entry of the periodic region, and the iteration it was started in
+    start: usize,
+    start_iteration: usize,
+    reported: bool,
+}
 
-    last_history_length: usize,
+enum CycleEvent {
+    Repeated(ActiveCycle),
+    Ended(ActiveCycle),
+}
+
+pub struct CycleDetector {
+    signal: CycleSignal,
+    thresholds: ThresholdPolicy,
+    search: CycleSearch<(String, u32)>,
+    // the last entry is not final yet (its count increases if the same value is selected again)
+    pending_entry: Option<(String, u32)>,
+    last_value: Option<String>,
+
+    active_cycles: Vec<ActiveCycle>,
+    // iteration in which each of the last history entries was started, and the predicate selected then; a cycle of the maximum
+    // size is found once it repeated twice, hence older entries are not needed
+    entries: VecDeque<(usize, Option<String>)>,
+    max_entries: usize,
+    pushed_entries: usize,
 }
 
 impl CycleDetector {
-    pub fn new() -> Self {
-        CycleDetector {
-            last_cycle: None,
-            last_cycle_end: 0,
+    pub fn new(signal: CycleSignal, cli: &Cli) -> Self {
+        let thresholds = ThresholdPolicy::new(cli.cycle_thresholds, &signal.to_string(), &cli.detector_cycle_thresholds, &cli.predicate_cycle_thresholds);
 
-            last_history_length: 0,
+        CycleDetector {
+            signal,
+            thresholds,
+            search: CycleSearch::new(cli.max_cycle_size),
+            pending_entry: None,
+            last_value: None,
+
+            active_cycles: Vec::new(),
+            entries: VecDeque::new(),
+            max_entries: 2 * cli.max_cycle_size,
+            pushed_entries: 0,
         }
     }
 
-    pub fn check_cycles(&mut self, history: &[(String, u32)], printer: &mut IterationSummary) {
-        // early out if no change in history
-        // this assumes that the passed fact histories are related.
-        if self.last_history_length == history.len() {
+    pub fn check_cycles(&mut self, iteration: &Iteration, printer: &mut IterationSummary) {
+        let value = self.signal.extract(iteration);
+
+        // keep aggregated history (repeated selections of the same value are counted)
+        let repeated = value.is_some() && value == self.last_value;
+        self.last_value = value.clone();
+        if repeated {
+            if let Some(pending_entry) = self.pending_entry.as_mut() {
+                pending_entry.1 += 1;
+            }
             return;
         }
 
-        // early-out if cycle potentially still active (avoids spamming smaller cycles in big cycle)
-        if self.last_cycle.is_some() {
-            if history.len() < self.last_cycle_end {
-                return;
-            }
+        let Some(value) = value else {
+            return;
+        };
 
-            self.last_cycle = None;
+        if let Some(pending_entry) = self.pending_entry.replace((value, 1)) {
+            for event in self.advance(pending_entry) {
+                self.report(event, printer);
+            }
         }
 
-        if let Some(cycle) = find_cycles(history) {
-            self.last_cycle = Some(cycle);
-            self.last_cycle_end = history.len() + cycle.size;
+        let predicate = iteration.selected_fact().and_then(|selected_fact| selected_fact.term.as_ref()).and_then(|term| term.predicate());
+        self.entries.push_back((iteration.progress.iteration, predicate.map(|predicate| predicate.to_string())));
+        self.pushed_entries += 1;
+        if self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+    }
 
-            if cycle.size * cycle.repeat > 1000 {
-                printer.add_error("Cycle".to_string(), format!("{:?}", cycle));
-                return;
-            }
+    /// iteration and predicate of the history entry, if it is still kept
+    fn entry(&self, index: usize) -> Option<&(usize, Option<String>)> {
+        let first_index = self.pushed_entries - self.entries.len();
+        self.entries.get(index.checked_sub(first_index)?)
+    }
 
-            if cycle.size * cycle.repeat > 100 {
-                printer.add_warning("Cycle".to_string(), format!("{:?}", cycle));
-                return;
+    fn report(&self, event: CycleEvent, printer: &mut IterationSummary) {
+        match event {
+            CycleEvent::Repeated(active_cycle) => {
+                if let Some(severity) = self.severity(&active_cycle) {
+                    let period_start = self.entry(self.pushed_entries - active_cycle.cycle.size).map_or(active_cycle.start_iteration, |entry| entry.0);
+                    printer.add_finding(severity, format!("{} cycle", self.signal), self.describe(&active_cycle));
+                    printer.add_loop(format!("{} cycle", self.signal), self.describe(&active_cycle), LoopSpan::Period(period_start));
+                }
             }
-
-            if cycle.size * cycle.repeat > 10 {
-                printer.add_info("Cycle".to_string(), format!("{:?}", cycle));
+            CycleEvent::Ended(active_cycle) => {
+                if active_cycle.reported {
+                    printer.add_info(format!("{} cycle ended", self.signal), self.describe(&active_cycle));
+                }
             }
         }
     }
-}
 
-fn find_cycles(history: &[(String, u32)]) -> Option<Cycle> {
-    let smallest_cycle_size = find_smallest_cycle_size(history);
-    if let Some(smallest_cycle_size) = smallest_cycle_size {
-        let number_of_cycles = find_number_of_cycles(history, smallest_cycle_size);
+    fn severity(&self, active_cycle: &ActiveCycle) -> Option<Severity> {
+        let cycle = active_cycle.cycle;
+        let period_start = active_cycle.start + (cycle.repeat - 1) * cycle.size;
+        let predicates = (period_start..period_start + cycle.size).filter_map(|index| self.entry(index)?.1.as_deref());
 
-        return Some(Cycle { size: smallest_cycle_size, repeat: number_of_cycles });
+        self.thresholds.thresholds(predicates).severity(cycle.size * cycle.repeat)
     }
 
-    None
-}
+    fn describe(&self, active_cycle: &ActiveCycle) -> String {
+        let mut description = format!("size {}, repeat {}, since iteration {}", active_cycle.cycle.size, active_cycle.cycle.repeat, active_cycle.start_iteration);
 
-fn find_smallest_cycle_size(history: &[(String, u32)]) -> Option<usize> {
-    let history_size = history.len();
-    let head_index = history_size - 1;
-    let head = &history[head_index];
+        for outer_cycle in self.outer_cycles(active_cycle) {
+            description = format!("{description}, within size {} cycle since iteration {}", outer_cycle.cycle.size, outer_cycle.start_iteration);
+        }
 
-    // only sensible if at least two entries
-    if history_size < 2 {
-        return None;
+        description
     }
-    let mut candidate_index = head_index - 1;
 
-    loop {
-        if history[candidate_index] == *head {
-            let expected_cycle_size = head_index - candidate_index;
+    fn outer_cycles(&self, active_cycle: &ActiveCycle) -> Vec<&ActiveCycle> {
+        let mut outer_cycles: Vec<&ActiveCycle> = self.active_cycles.iter().filter(|other| other.cycle.size > active_cycle.cycle.size && other.start <= active_cycle.start).collect();
+        outer_cycles.sort_by_key(|other| other.cycle.size);
 
-            // check for cycle
-            let mut head_check = head_index - 1;
-            while head_check > candidate_index && head_check >= expected_cycle_size {
-                if history[head_check - expected_cycle_size] != history[head_check] {
-                    break;
-                }
+        outer_cycles
+    }
 
-                head_check -= 1;
+    /// appends the entry to the history, and returns the cycles which have been extended or have ended
+    fn advance(&mut self, entry: (String, u32)) -> Vec<CycleEvent> {
+        let mut events = Vec::new();
+        let candidates = self.search.push(entry);
+        let history_length = self.search.len();
+
+        // continue or end active cycles
+        let mut active_cycles = Vec::new();
+        for mut active_cycle in std::mem::take(&mut self.active_cycles) {
+            let size = active_cycle.cycle.size;
+            if !candidates.iter().any(|candidate| candidate.size == size) {
+                events.push(CycleEvent::Ended(active_cycle));
+                continue;
             }
 
-            // cycle found
-            if head_check == candidate_index {
-                return Some(expected_cycle_size);
+            let repeat = (history_length - active_cycle.start) / size;
+            if repeat > active_cycle.cycle.repeat {
+                active_cycle.cycle.repeat = repeat;
+                active_cycle.reported |= self.severity(&active_cycle).is_some();
+                events.push(CycleEvent::Repeated(active_cycle));
             }
+            active_cycles.push(active_cycle);
         }
+        self.active_cycles = active_cycles;
+
+        // find new cycles
+        for candidate in candidates {
+            let size = candidate.size;
+            let window_start = history_length - 2 * size;
+            let already_active = self.active_cycles.iter().any(|active_cycle| size % active_cycle.cycle.size == 0 && active_cycle.start <= window_start);
+            if already_active {
+                continue;
+            }
 
-        if candidate_index == 0 {
-            break;
+            let repeat = (history_length - candidate.start) / size;
+            let mut active_cycle = ActiveCycle {
+                cycle: Cycle { size, repeat },
+                start: candidate.start,
+                start_iteration: self.entry(candidate.start).map_or(0, |entry| entry.0),
+                reported: false,
+            };
+            active_cycle.reported = self.severity(&active_cycle).is_some();
+
+            self.active_cycles.push(active_cycle);
+            events.push(CycleEvent::Repeated(active_cycle));
         }
 
-        candidate_index -= 1;
+        events
     }
-
-    None
 }
 
-fn find_number_of_cycles(history: &[(String, u32)], cycle_size: usize) -> usize {
-    let history_len = history.len();
-    // only sensible if at least two cycles entries
-    if history_len < cycle_size {
-        return 0;
+impl CycleSignal {
+    fn extract(&self, iteration: &Iteration) -> Option<String> {
+        match self {
+            CycleSignal::Hypothesis => iteration.hypothesis_fact_selected.as_ref().map(|selected_fact| selected_fact.fact.clone()),
+            CycleSignal::Conclusion => iteration.conclusion_fact_selected.as_ref().map(|selected_fact| selected_fact.fact.clone()),
+            CycleSignal::Query => Some(canonicalize(&iteration.query)),
+            CycleSignal::Predicate => {
+                let kind = if iteration.hypothesis_fact_selected.is_some() { "hypothesis" } else { "conclusion" };
+                let predicate = iteration.selected_fact()?.term.as_ref()?.predicate()?;
+                Some(format!("{kind} {predicate}"))
+            }
+        }
     }
+}
 
-    let mut head_index = history_len - 1;
+impl Display for CycleSignal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CycleSignal::Hypothesis => "Hypothesis",
+            CycleSignal::Conclusion => "Conclusion",
+            CycleSignal::Query => "Query",
+            CycleSignal::Predicate => "Predicate",
+        };
 
-    loop {
-        let candidate = head_index - cycle_size;
-        if history[candidate] != history[head_index] {
-            head_index += 1;
-            break;
-        }
-
-        if candidate == 0 {
-            break;
-        }
+        write!(f, "{name}")
+    }
+}
 
-        head_index -= 1
+#[cfg(test)]
+fn find_cycles(history: &[(String, u32)]) -> Option<Cycle> {
+    let mut cycle_detector = CycleDetector::new(CycleSignal::Hypothesis, &<Cli as clap::Parser>::parse_from(["proverif_terminator"]));
+    for entry in history {
+        cycle_detector.advance(entry.clone());
     }
 
-    let correct_streak = history_len - (head_index - cycle_size);
-    correct_streak / cycle_size
+    cycle_detector.active_cycles.iter().map(|active_cycle| active_cycle.cycle).min_by_key(|cycle| cycle.size)
 }
 
 #[test]
@@ -166,3 +270,47 @@ fn test_find_number_of_cycles_multiple_cycles() {
     let history = vec![("a".to_string(), 1), ("a".to_string(), 2), ("a".to_string(), 1), ("a".to_string(), 2), ("a".to_string(), 1), ("a".to_string(), 2)];
     assert_eq!(find_cycles(&history), Some(Cycle { size: 2, repeat: 3 }));
 }
+
+#[test]
+fn test_nested_cycles() {
+    // inner cycle "ab" within outer cycle "ababcd"
+    let history: Vec<(String, u32)> = "ababcdababcdababcdabab".chars().map(|c| (c.to_string(), 1)).collect();
+
+    let mut cycle_detector = CycleDetector::new(CycleSignal::Hypothesis, &<Cli as clap::Parser>::parse_from(["proverif_terminator"]));
+    let mut ended_sizes = Vec::new();
+    for entry in history {
+        for event in cycle_detector.advance(entry) {
+            if let CycleEvent::Ended(active_cycle) = event {
+                ended_sizes.push(active_cycle.cycle.size);
+            }
+        }
+    }
+
+    // inner cycle ended once per outer repetition, both still active at the end
+    assert_eq!(ended_sizes, vec![2, 2, 2]);
+    let mut active_cycles: Vec<(usize, usize, usize)> = cycle_detector.active_cycles.iter().map(|c| (c.cycle.size, c.cycle.repeat, c.start)).collect();
+    active_cycles.sort();
+    assert_eq!(active_cycles, vec![(2, 2, 18), (6, 3, 0)]);
+
+    let inner_cycle = cycle_detector.active_cycles.iter().find(|c| c.cycle.size == 2).unwrap();
+    assert_eq!(cycle_detector.outer_cycles(inner_cycle).len(), 1);
+}
+
+#[test]
+fn test_cycle_detector_forgets_old_entries() {
+    use crate::saturation_state::Iteration;
+
+    let cli = <Cli as clap::Parser>::parse_from(["proverif_terminator", "--max-cycle-size", "4"]);
+    let mut cycle_detector = CycleDetector::new(CycleSignal::Hypothesis, &cli);
+    let mut descriptions = Vec::new();
+    for index in 0..1000 {
+        let fact = format!("att2(f{}(x_1),x_1)", index % 3);
+        let iteration = Iteration::for_test(index + 1, &format!("{fact} -> att2(x_1,x_1)"), Some(&fact));
+        let mut summary = IterationSummary::for_test();
+        cycle_detector.check_cycles(&iteration, &mut summary);
+        descriptions.extend(summary.findings().iter().map(|(_, _, line)| line.to_string()));
+    }
+
+    assert_eq!(cycle_detector.entries.len(), 8);
+    assert!(descriptions.last().unwrap().ends_with("since iteration 1"));
+}
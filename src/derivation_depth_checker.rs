@@ -0,0 +1,62 @@
+use crate::iteration_summary::IterationSummary;
+use crate::report_policy::Thresholds;
+use crate::saturation_state::Iteration;
+use crate::witness::LoopSpan;
+use crate::Cli;
+
+/// warns when the derivation depth keeps increasing, i.e. the clauses are derived along an ever longer chain of resolution steps
+pub struct DerivationDepthChecker {
+    thresholds: Thresholds,
+    maximum: usize,
+    // depth at which the chain was reported last, it is reported again once it grew by the info threshold
+    reported_depth: usize,
+}
+
+impl DerivationDepthChecker {
+    pub fn new(cli: &Cli) -> Self {
+        DerivationDepthChecker {
+            thresholds: cli.derivation_depth_thresholds,
+            maximum: 0,
+            reported_depth: 0,
+        }
+    }
+
+    pub fn check(&mut self, iteration: &Iteration, printer: &mut IterationSummary) {
+        let Some(depth) = iteration.derivation_depth else {
+            return;
+        };
+        if depth <= self.maximum {
+            return;
+        }
+        self.maximum = depth;
+
+        let Some(severity) = self.thresholds.severity(depth) else {
+            return;
+        };
+        if severity != self.thresholds.severity(self.reported_depth).unwrap_or(severity) || depth >= self.reported_depth + self.thresholds.info.max(1) {
+            self.reported_depth = depth;
+            let description = format!("selected clause is {depth} resolution steps from an initial clause, the deepest so far");
+            printer.add_finding(severity, "Derivation depth".to_string(), description.clone());
+            printer.add_loop("Derivation depth".to_string(), description, LoopSpan::Ancestry);
+        }
+    }
+}
+
+#[test]
+fn test_derivation_depth_checker() {
+    use clap::Parser;
+
+    let cli = Cli::parse_from(["proverif_terminator", "--derivation-depth-thresholds", "2,5,100"]);
+    let mut checker = DerivationDepthChecker::new(&cli);
+    let mut depths = vec![];
+    for depth in [0, 1, 3, 2, 4, 5, 6, 7, 8] {
+        let mut iteration = Iteration::for_test(depth, "", None);
+        iteration.derivation_depth = Some(depth);
+        let mut summary = IterationSummary::for_test();
+        checker.check(&iteration, &mut summary);
+        depths.extend(summary.findings().iter().map(|(severity, _, _)| format!("{depth} {severity:?}")));
+    }
+
+    // reported when the severity rises, or the chain grew by the info threshold since the last report
+    assert_eq!(depths, vec!["3 Info", "5 Info", "6 Warning", "8 Warning"]);
+}
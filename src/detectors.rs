@@ -0,0 +1,154 @@
+use crate::bi_fact_checker::BiFactChecker;
+use crate::clause_tracker::ClauseTracker;
+use crate::counter_checker::CounterChecker;
+use crate::cycles::CycleDetector;
+use crate::derivation_depth_checker::DerivationDepthChecker;
+use crate::fact_checker::FactChecker;
+use crate::iteration_summary::IterationSummary;
+use crate::queue_checker::QueueChecker;
+use crate::replication_checker::ReplicationChecker;
+use crate::saturation_state::Iteration;
+use crate::term::Side;
+use crate::term_metrics_checker::TermMetricsChecker;
+use crate::Cli;
+
+pub struct Detectors {
+    fact_checker: FactChecker,
+    counter_checker: CounterChecker,
+    bi_fact_checker: BiFactChecker,
+    queue_checker: QueueChecker,
+    derivation_depth_checker: DerivationDepthChecker,
+    replication_checker: ReplicationChecker,
+    both_sides: SideDetectors,
+    per_side: Vec<(Side, SideDetectors)>,
+}
+
+/// detectors which run on the iteration as is, or on the projection to one side of the biprocess
+struct SideDetectors {
+    cycle_detectors: Vec<CycleDetector>,
+    term_metrics_checker: TermMetricsChecker,
+}
+
+impl Detectors {
+    pub fn new(cli: &Cli) -> Self {
+        let per_side = match cli.per_side {
+            true => vec![(Side::Left, SideDetectors::new(cli)), (Side::Right, SideDetectors::new(cli))],
+            false => vec![],
+        };
+
+        Detectors {
+            fact_checker: FactChecker::new(cli),
+            counter_checker: CounterChecker::new(),
+            bi_fact_checker: BiFactChecker::new(),
+            queue_checker: QueueChecker::new(cli),
+            derivation_depth_checker: DerivationDepthChecker::new(cli),
+            replication_checker: ReplicationChecker::new(),
+            both_sides: SideDetectors::new(cli),
+            per_side,
+        }
+    }
+
+    pub fn check(&mut self, cli: &Cli, iteration: &Iteration, clauses: &ClauseTracker, iteration_summary: &mut IterationSummary) {
+        self.both_sides.check_cycles(cli, iteration, iteration_summary);
+
+        if let Some(fact) = iteration.hypothesis_fact_selected.as_ref().and_then(|selected_fact| selected_fact.term.as_ref()) {
+            self.fact_checker.check(fact, iteration_summary)
+        }
+        if cli.detect_all || cli.detect_counters {
+            self.counter_checker.check(iteration, iteration_summary);
+        }
+
+        if let Some(term_metrics) = iteration.term_metrics() {
+            if cli.all || cli.print_all || cli.print_term_metrics {
+                iteration_summary.add_column(term_metrics.to_string());
+            }
+        }
+        self.both_sides.check_term_metrics(cli, iteration, iteration_summary);
+
+        if cli.detect_all || cli.detect_bi_facts {
+            self.bi_fact_checker.check(iteration, iteration_summary);
+        }
+
+        if cli.detect_all || cli.detect_queue {
+            self.queue_checker.check(iteration, clauses, iteration_summary);
+        }
+
+        if let Some(derivation_depth) = iteration.derivation_depth {
+            if cli.all || cli.print_all || cli.print_derivation_depth {
+                iteration_summary.add_column(format!("depth {derivation_depth}"));
+            }
+        }
+        if cli.detect_all || cli.detect_derivation_depth {
+            self.derivation_depth_checker.check(iteration, iteration_summary);
+        }
+
+        if cli.detect_all || cli.detect_replication {
+            self.replication_checker.check(iteration, iteration_summary);
+        }
+
+        for (side, side_detectors) in self.per_side.iter_mut() {
+            let projected_iteration = iteration.project(*side);
+            let mut side_summary = IterationSummary::new(String::new(), String::new(), vec![], String::new());
+            side_detectors.check_cycles(cli, &projected_iteration, &mut side_summary);
+            side_detectors.check_term_metrics(cli, &projected_iteration, &mut side_summary);
+
+            // the side detectors still see iterations without sides, so that their cycles span the same iterations,
+            // but the findings of their projections would only repeat the findings above
+            if !iteration.has_sides() {
+                continue;
+            }
+            for (severity, header, line) in side_summary.findings() {
+                iteration_summary.add_finding(severity, format!("{header} ({side})"), line.clone());
+            }
+            for loop_finding in side_summary.loops() {
+                iteration_summary.add_loop(format!("{} ({side})", loop_finding.header), loop_finding.description.clone(), loop_finding.span);
+            }
+        }
+    }
+}
+
+impl SideDetectors {
+    fn new(cli: &Cli) -> Self {
+        SideDetectors {
+            cycle_detectors: cli.cycle_signals.iter().map(|signal| CycleDetector::new(*signal, cli)).collect(),
+            term_metrics_checker: TermMetricsChecker::new(cli),
+        }
+    }
+
+    fn check_cycles(&mut self, cli: &Cli, iteration: &Iteration, iteration_summary: &mut IterationSummary) {
+        if cli.detect_all || cli.detect_cycles {
+            for cycle_detector in self.cycle_detectors.iter_mut() {
+                cycle_detector.check_cycles(iteration, iteration_summary);
+            }
+        }
+    }
+
+    fn check_term_metrics(&mut self, cli: &Cli, iteration: &Iteration, iteration_summary: &mut IterationSummary) {
+        if let Some(term_metrics) = iteration.term_metrics() {
+            if cli.detect_all || cli.detect_term_metrics {
+                self.term_metrics_checker.check(&term_metrics, iteration_summary);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_per_side_cycles_span_iterations_without_sides() {
+    let cli = <Cli as clap::Parser>::parse_from(["proverif_terminator", "--detect-cycles", "--per-side"]);
+    let mut detectors = Detectors::new(&cli);
+    let clauses = ClauseTracker::new();
+
+    // a c b c a c b c ...: only a and b have sides, and the left side sees a size 4 cycle, not a b a b
+    let facts = ["att2(a(x_1),f(y_1))", "mess(c[],x_1)", "att2(b(x_1),f(y_1))", "mess(c[],x_1)"];
+    let mut left_loops = Vec::new();
+    for index in 0..16 {
+        let fact = facts[index % facts.len()];
+        let iteration = Iteration::for_test(index + 1, &format!("{fact} -> mess(d[],x_1)"), Some(fact));
+        let mut summary = IterationSummary::for_test();
+        detectors.check(&cli, &iteration, &clauses, &mut summary);
+        left_loops.extend(summary.loops().iter().filter(|loop_finding| loop_finding.header.ends_with("(left)")).map(|loop_finding| loop_finding.description.clone()));
+    }
+
+    assert!(!left_loops.is_empty());
+    assert!(left_loops.iter().all(|description| description.starts_with("size 4")));
+}
@@ -1,62 +1,87 @@
+use crate::counter_checker::collect_integers;
 use crate::iteration_summary::IterationSummary;
+use crate::term::Term;
 use crate::Cli;
-use regex::Regex;
-use std::collections::HashMap;
+
+// integers with two digits are unlikely to be constants of the model
+const HIGH_COUNTER: u64 = 10;
 
 pub struct FactChecker {
-    regex_map: HashMap<&'static str, Vec<Regex>>,
+    detect_high_counters: bool,
 }
 
 impl FactChecker {
     pub fn new(cli: &Cli) -> Self {
-        let templates_map = compose_targets(cli);
-        let regex_map = compile_targets(templates_map);
-
-        FactChecker { regex_map }
+        FactChecker {
+            detect_high_counters: cli.all || cli.detect_all || cli.detect_high_counters,
+        }
     }
 
-    pub fn check(&self, fact: &str, printer: &mut IterationSummary) {
-        for group in self.regex_map.iter() {
-            let group_header = group.0;
-            let group_entries = group.1;
+    /// reports every argument of the fact holding a high integer, wherever it is nested
+    pub fn check(&self, fact: &Term, printer: &mut IterationSummary) {
+        if !self.detect_high_counters {
+            return;
+        }
+        let Term::Function(predicate, arguments) = fact else {
+            return;
+        };
+
+        // user events are facts `event(e(...))`, their arguments are the arguments of `e`
+        let (predicate, arguments) = match (predicate.as_str(), arguments.as_slice()) {
+            ("event" | "inj-event" | "end", [Term::Function(event, event_arguments)]) => (format!("event {event}"), event_arguments),
+            _ => (predicate.clone(), arguments),
+        };
 
-            for regex in group_entries.iter() {
-                if !regex.is_match(fact) {
-                    continue;
-                }
+        for (index, argument) in arguments.iter().enumerate() {
+            let mut integers = Vec::new();
+            collect_integers(argument, format!("{}.{index}", predicate.replace(' ', ".")), &mut integers);
 
-                printer.add_warning(format!("{} pattern", group_header), regex.to_string());
+            for (path, value) in integers.into_iter().filter(|(_, value)| *value >= HIGH_COUNTER) {
+                let role = argument_role(&predicate, index, arguments.len());
+                printer.add_warning("HighCounter".to_string(), format!("{value} in the {role} of {predicate} (at {path})"));
             }
         }
     }
 }
 
-fn compose_targets(cli: &Cli) -> HashMap<&'static str, Vec<&'static str>> {
-    let all = cli.all || cli.detect_all;
-
-    let mut templates_map: HashMap<&str, Vec<&str>> = HashMap::new();
-    if all || cli.detect_high_counters {
-        templates_map.insert(
-            "HighCounter",
-            vec![
-                r"mess2\(.+,[0-9]{2,},.+\)",          // detect 2-digit number in first channel
-                r"mess2\(.+,[0-9]{2,}\)",             // detect 2-digit number in second channel
-                r"table2\(.+[,\(][0-9]{2,}[,\(].+\)", // detect 2-digit number in table
-            ],
-        );
-    }
+/// what an argument of one of the standard ProVerif predicates holds, by position otherwise
+fn argument_role(predicate: &str, index: usize, arity: usize) -> String {
+    let roles: &[&str] = match predicate {
+        "attacker" | "att" => &["message"],
+        "att2" => &["left message", "right message"],
+        "mess" => &["channel", "message"],
+        "mess2" => &["left channel", "left message", "right channel", "right message"],
+        "table" => &["table entry"],
+        "table2" => &["left table entry", "right table entry"],
+        "input" | "output" => &["channel"],
+        "input2" | "output2" => &["left channel", "right channel"],
+        _ => &[],
+    };
 
-    templates_map
+    match roles.get(index) {
+        Some(role) if roles.len() == arity => role.to_string(),
+        _ => format!("argument {}", index + 1),
+    }
 }
 
-fn compile_targets(templates_map: HashMap<&'static str, Vec<&str>>) -> HashMap<&'static str, Vec<Regex>> {
-    let regex_map: HashMap<&'static str, Vec<Regex>> = templates_map
-        .iter()
-        .map(|(k, v)| {
-            let compiled_regexes = v.iter().map(|pattern| Regex::new(pattern).unwrap()).collect();
-            (*k, compiled_regexes)
-        })
-        .collect();
+#[test]
+fn test_high_counters() {
+    use clap::Parser;
+
+    let checker = FactChecker::new(&Cli::parse_from(["proverif_terminator", "--detect-high-counters"]));
+    let high_counters = |fact: &str| {
+        let mut summary = IterationSummary::for_test();
+        checker.check(&Term::parse(fact).unwrap(), &mut summary);
+        summary.findings().iter().map(|(_, _, line)| line.to_string()).collect::<Vec<_>>()
+    };
 
-    regex_map
+    assert_eq!(high_counters("mess2(c[],(12,x_1),c[],(9,x_1))"), vec!["12 in the left message of mess2 (at mess2.1.0)"]);
+    assert_eq!(
+        high_counters("table2(cell(k[!1 = @sid],10),cell(k[!1 = @sid],11))"),
+        vec!["10 in the left table entry of table2 (at table2.0.1)", "11 in the right table entry of table2 (at table2.1.1)"]
+    );
+    assert_eq!(high_counters("att(f(x_1,100))"), vec!["100 in the message of att (at att.0.1)"]);
+    assert_eq!(high_counters("event(sent(x_1,42))"), vec!["42 in the argument 2 of event sent (at event.sent.1)"]);
+    assert_eq!(high_counters("counter(a[],7,(11,x_1))"), vec!["11 in the argument 3 of counter (at counter.2.0)"]);
+    assert!(high_counters("mess2(c[],x_1,c[],x_1)").is_empty());
 }
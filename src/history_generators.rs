@@ -0,0 +1,23 @@
+//! histories for the tests and the benchmark of the cycle search
+
+/// pseudo-random history entries (linear congruential generator, to stay reproducible without dependencies)
+pub fn generate_random_history(length: usize, alphabet_size: u64) -> Vec<(String, u32)> {
+    let mut state: u64 = 42;
+    (0..length)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (format!("fact{}", (state >> 33) % alphabet_size), 1)
+        })
+        .collect()
+}
+
+/// an outer cycle of size 12 containing an inner cycle of size 2, interrupted every few thousand entries
+pub fn generate_nested_history(length: usize) -> Vec<(String, u32)> {
+    let pattern = ["a", "b", "a", "b", "a", "b", "c", "d", "e", "f", "g", "h"];
+    (0..length)
+        .map(|index| match index % 5000 {
+            0 => (format!("break{index}"), 1),
+            _ => (pattern[index % pattern.len()].to_string(), 1),
+        })
+        .collect()
+}
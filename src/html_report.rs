@@ -0,0 +1,254 @@
+use crate::report_policy::Finding;
+use crate::rule_explainer::QueryExplainer;
+use crate::saturation_state::{Iteration, SaturationState};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+const CHART_WIDTH: usize = 1000;
+const CHART_HEIGHT: usize = 250;
+// computing the ancestry is linear in the number of iterations, hence only done for the first flagged iterations
+const MAX_EXPLAINED_ITERATIONS: usize = 100;
+const MAX_NEVER_SELECTED_CLAUSES: usize = 1000;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; } table { border-collapse: collapse; } td, th { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: left; } \
+    code { font-size: 0.9em; } .Info { color: #a08000; } .Warning { color: #d06000; } .Error { color: #c00000; } .queue { stroke: #1f77b4; } .base { stroke: #2ca02c; }";
+const SEARCH_SCRIPT: &str = "document.getElementById('fact-search').addEventListener('input', function (e) { \
+    const needle = e.target.value.toLowerCase(); \
+    document.querySelectorAll('#facts tbody tr').forEach(function (row) { row.style.display = row.textContent.toLowerCase().includes(needle) ? '' : 'none'; }); });";
+
+/// self-contained HTML page summarizing a saturation
+pub struct HtmlReport<'a> {
+    saturation_state: &'a SaturationState,
+    iterations: &'a [Iteration],
+    findings: &'a [Finding],
+}
+
+impl<'a> HtmlReport<'a> {
+    pub fn new(saturation_state: &'a SaturationState, findings: &'a [Finding]) -> Self {
+        HtmlReport {
+            saturation_state,
+            iterations: &saturation_state.iterations,
+            findings,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.render())
+    }
+
+    fn render(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>ProVerif Terminator report</title>\n");
+        let _ = writeln!(html, "<style>{STYLE}</style>\n</head>\n<body>\n<h1>ProVerif Terminator report</h1>");
+
+        if let Some(last_iteration) = self.iterations.last() {
+            let _ = writeln!(html, "<p>{} iterations, last progress {}, {} findings</p>", self.iterations.len(), last_iteration.progress, self.findings.len());
+        }
+
+        self.render_saturations(&mut html);
+        self.render_chart(&mut html);
+        self.render_predicates(&mut html);
+        self.render_findings(&mut html);
+        self.render_flagged_iterations(&mut html);
+        self.render_facts(&mut html);
+        self.render_queue(&mut html);
+        self.render_never_selected(&mut html);
+
+        let _ = writeln!(html, "<script>{SEARCH_SCRIPT}</script>\n</body>\n</html>");
+        html
+    }
+
+    fn render_saturations(&self, html: &mut String) {
+        let saturations = &self.saturation_state.saturations;
+        if saturations.iter().all(|saturation| saturation.description.is_none()) {
+            return;
+        }
+
+        html.push_str("<h2>Queries</h2>\n<table>\n<thead><tr><th>Query</th><th>Iterations</th><th>Saturation</th><th>Findings</th><th>Results</th></tr></thead>\n<tbody>\n");
+        for (position, saturation) in saturations.iter().enumerate() {
+            let end = saturations.get(position + 1).map(|next| next.first_iteration_index).unwrap_or(self.iterations.len());
+            let findings = self.findings.iter().filter(|finding| (saturation.first_iteration_index..end).contains(&finding.iteration_index)).count();
+            let results: Vec<String> = saturation.results.iter().map(|result| escape(result)).collect();
+            let _ = writeln!(
+                html,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{:?}</td><td>{findings}</td><td>{}</td></tr>",
+                escape(saturation.description.as_deref().unwrap_or("")),
+                end - saturation.first_iteration_index,
+                saturation.phase,
+                results.join("<br>")
+            );
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+
+    fn render_chart(&self, html: &mut String) {
+        html.push_str("<h2>Queue and base</h2>\n");
+        if self.iterations.is_empty() {
+            return;
+        }
+
+        let step = self.iterations.len().div_ceil(CHART_WIDTH);
+        let samples: Vec<&Iteration> = self.iterations.iter().step_by(step).collect();
+        let base_size = |iteration: &Iteration| iteration.progress.with_conclusion_selected + iteration.progress.with_hypothesis_selected;
+        let maximum = samples.iter().map(|i| i.progress.in_queue.max(base_size(i))).max().unwrap_or(0).max(1);
+
+        let points = |value: &dyn Fn(&Iteration) -> usize| -> String {
+            let x_scale = CHART_WIDTH as f64 / samples.len().max(2).saturating_sub(1) as f64;
+            let points: Vec<String> = samples
+                .iter()
+                .enumerate()
+                .map(|(index, iteration)| format!("{:.1},{:.1}", index as f64 * x_scale, CHART_HEIGHT as f64 * (1.0 - value(iteration) as f64 / maximum as f64)))
+                .collect();
+            points.join(" ")
+        };
+
+        let _ = writeln!(html, "<svg width=\"{CHART_WIDTH}\" height=\"{CHART_HEIGHT}\" viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\" style=\"border: 1px solid #ccc\">");
+        let _ = writeln!(html, "<polyline class=\"queue\" fill=\"none\" points=\"{}\"/>", points(&|i: &Iteration| i.progress.in_queue));
+        let _ = writeln!(html, "<polyline class=\"base\" fill=\"none\" points=\"{}\"/>", points(&base_size));
+        html.push_str("</svg>\n");
+
+        let first = self.iterations.first().unwrap().progress.iteration;
+        let last = self.iterations.last().unwrap().progress.iteration;
+        let _ = writeln!(html, "<p>Iterations {first} to {last}; maximum {maximum} rules; <span class=\"queue\" style=\"color: #1f77b4\">queue</span>, <span style=\"color: #2ca02c\">base</span></p>");
+    }
+
+    fn render_predicates(&self, html: &mut String) {
+        let mut selections: BTreeMap<(String, &str), usize> = BTreeMap::new();
+        for iteration in self.iterations {
+            let kind = if iteration.hypothesis_fact_selected.is_some() { "hypothesis" } else { "conclusion" };
+            let predicate = iteration.selected_fact().and_then(|f| f.term.as_ref()).and_then(|t| t.predicate()).unwrap_or("?");
+            *selections.entry((predicate.to_string(), kind)).or_default() += 1;
+        }
+
+        html.push_str("<h2>Predicates</h2>\n<table>\n<thead><tr><th>Predicate</th><th>Selected as</th><th>Iterations</th><th>Share</th></tr></thead>\n<tbody>\n");
+        for ((predicate, kind), count) in selections.iter() {
+            let share = 100.0 * *count as f64 / self.iterations.len() as f64;
+            let _ = writeln!(html, "<tr><td><code>{}</code></td><td>{kind}</td><td>{count}</td><td>{share:.1}%</td></tr>", escape(predicate));
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+
+    fn render_findings(&self, html: &mut String) {
+        html.push_str("<h2>Findings</h2>\n<table>\n<thead><tr><th>Iteration</th><th>Severity</th><th>Finding</th><th>Details</th></tr></thead>\n<tbody>\n");
+        for finding in self.findings {
+            let _ = writeln!(
+                html,
+                "<tr><td><a href=\"#iteration-{}\">{}</a></td><td class=\"{:?}\">{:?}</td><td>{}</td><td>{}</td></tr>",
+                finding.iteration_index,
+                finding.iteration,
+                finding.severity,
+                finding.severity,
+                escape(&finding.header),
+                escape(&finding.line)
+            );
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+
+    fn render_flagged_iterations(&self, html: &mut String) {
+        let mut findings_per_iteration: BTreeMap<usize, Vec<&Finding>> = BTreeMap::new();
+        for finding in self.findings {
+            findings_per_iteration.entry(finding.iteration_index).or_default().push(finding);
+        }
+
+        html.push_str("<h2>Flagged iterations</h2>\n");
+        for (position, (iteration_index, findings)) in findings_per_iteration.iter().enumerate() {
+            let Some(iteration) = self.iterations.get(*iteration_index) else {
+                continue;
+            };
+
+            let selected_fact = iteration.selected_fact().map(|f| f.fact.as_str()).unwrap_or("");
+            let _ = writeln!(html, "<details id=\"iteration-{iteration_index}\">\n<summary>{} <code>{}</code> ({} findings)</summary>", iteration.progress, escape(selected_fact), findings.len());
+            let _ = writeln!(html, "<p>Query: <code>{}</code></p>\n<ul>", escape(&iteration.query));
+            for finding in findings {
+                let _ = writeln!(html, "<li class=\"{:?}\">{}: {}</li>", finding.severity, escape(&finding.header), escape(&finding.line));
+            }
+            html.push_str("</ul>\n");
+
+            if position < MAX_EXPLAINED_ITERATIONS {
+                html.push_str("<p>Ancestors:</p>\n<ol>\n");
+                let saturation = self.saturation_state.saturation_of(*iteration_index);
+                let ancestry = QueryExplainer::get_ancestry(&self.iterations[saturation.first_iteration_index..=*iteration_index], &saturation.initial_clauses);
+                if let Some(root) = QueryExplainer::get_root(&ancestry, &saturation.initial_clauses) {
+                    let _ = writeln!(html, "<li value=\"0\">{}</li>", escape(&root.to_string()));
+                }
+                for ancestor in ancestry.iter().rev() {
+                    let iteration = &ancestor.iteration;
+                    let link = format!("{}, confidence {:.1}", ancestor.link, ancestor.link.confidence());
+                    let _ = writeln!(html, "<li>{}: <code>{}</code> <small>({link})</small></li>", iteration.progress.iteration, escape(&iteration.query));
+                }
+                html.push_str("</ol>\n");
+            }
+            html.push_str("</details>\n");
+        }
+    }
+
+    fn render_facts(&self, html: &mut String) {
+        let mut facts: HashMap<(&str, &str), (usize, usize)> = HashMap::new();
+        for iteration in self.iterations {
+            let kind = if iteration.hypothesis_fact_selected.is_some() { "hypothesis" } else { "conclusion" };
+            if let Some(selected_fact) = iteration.selected_fact() {
+                let entry = facts.entry((kind, &selected_fact.fact)).or_insert((iteration.progress.iteration, 0));
+                entry.1 += 1;
+            }
+        }
+
+        let mut facts: Vec<_> = facts.into_iter().collect();
+        facts.sort_by_key(|(_, (first_iteration, _))| *first_iteration);
+
+        html.push_str("<h2>Selected facts</h2>\n<input id=\"fact-search\" type=\"search\" placeholder=\"Filter facts\" size=\"60\">\n");
+        html.push_str("<table id=\"facts\">\n<thead><tr><th>First iteration</th><th>Selected</th><th>Kind</th><th>Fact</th></tr></thead>\n<tbody>\n");
+        for ((kind, fact), (first_iteration, count)) in facts {
+            let _ = writeln!(html, "<tr><td>{first_iteration}</td><td>{count}</td><td>{kind}</td><td><code>{}</code></td></tr>", escape(fact));
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+
+    fn render_queue(&self, html: &mut String) {
+        html.push_str("<h2>Queue per predicate</h2>\n<table>\n<thead><tr><th>Query</th><th>Predicate</th><th>Queued</th><th>Selected</th><th>Average waiting time</th><th>Still queued</th><th>Average age</th></tr></thead>\n<tbody>\n");
+        for saturation in self.saturation_state.saturations.iter() {
+            for (predicate, statistics) in saturation.clauses.predicate_statistics() {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{:.1}</td><td>{}</td><td>{:.1}</td></tr>",
+                    escape(saturation.description.as_deref().unwrap_or("")),
+                    escape(predicate),
+                    statistics.queued,
+                    statistics.selected,
+                    statistics.average_waiting_time,
+                    statistics.still_queued,
+                    statistics.average_age
+                );
+            }
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+
+    fn render_never_selected(&self, html: &mut String) {
+        html.push_str("<h2>Never selected clauses</h2>\n<table>\n<thead><tr><th>Query</th><th>Rule</th><th>Queued at</th><th>Dropped at</th><th>Clause</th></tr></thead>\n<tbody>\n");
+        for saturation in self.saturation_state.saturations.iter() {
+            for clause in saturation.clauses.never_selected().take(MAX_NEVER_SELECTED_CLAUSES) {
+                let dropped = clause.dropped_iteration.map(|iteration| iteration.to_string()).unwrap_or("still queued".to_string());
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{dropped}</td><td><code>{}</code></td></tr>",
+                    escape(saturation.description.as_deref().unwrap_or("")),
+                    clause.entry.number,
+                    clause.enqueued_iteration,
+                    escape(&clause.entry.rule)
+                );
+            }
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(escape("x_1 <> y_2 && \"a\""), "x_1 &lt;&gt; y_2 &amp;&amp; &quot;a&quot;");
+}
@@ -0,0 +1,153 @@
+use crate::iteration_summary::Severity;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, StdinLock, Write};
+use std::path::Path;
+
+/// reads the ProVerif log line by line, copying it to the tee file if requested
+pub struct Input {
+    stdin: StdinLock<'static>,
+    pub tee: Option<Tee>,
+    pub error: Option<io::Error>,
+}
+
+impl Input {
+    pub fn new(tee: Option<Tee>) -> Self {
+        Input { stdin: io::stdin().lock(), tee, error: None }
+    }
+
+    /// returns `None` at the end of the input; invalid UTF-8 is replaced, and read errors end the input
+    pub fn read_line(&mut self) -> Option<String> {
+        let mut bytes = Vec::new();
+        let read = self.stdin.read_until(b'\n', &mut bytes).unwrap_or_else(|error| {
+            self.error = Some(error);
+            0
+        });
+        if read == 0 {
+            if let Some(tee) = self.tee.as_mut() {
+                tee.write_pending_line();
+            }
+            return None;
+        }
+
+        // the copy keeps the raw bytes, only the analysed line is decoded
+        let line = String::from_utf8_lossy(&bytes).into_owned();
+        if let Some(tee) = self.tee.as_mut() {
+            tee.write_line(bytes);
+        }
+
+        Some(line)
+    }
+}
+
+enum TeeWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+/// copy of the raw log, optionally annotated with the findings
+///
+/// A line is only written once the next one is read, so that the findings of an iteration (known once the next iteration starts)
+/// end up right after the lines of that iteration.
+pub struct Tee {
+    writer: TeeWriter,
+    annotate: bool,
+    pending_line: Option<Vec<u8>>,
+    error: Option<io::Error>,
+}
+
+impl Tee {
+    /// compresses with gzip if the file name ends in `.gz`
+    pub fn create(path: &Path, annotate: bool) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let writer = match path.extension() {
+            Some(extension) if extension == "gz" => TeeWriter::Gzip(GzEncoder::new(file, Compression::default())),
+            _ => TeeWriter::Plain(file),
+        };
+
+        Ok(Tee { writer, annotate, pending_line: None, error: None })
+    }
+
+    fn write_line(&mut self, line: Vec<u8>) {
+        if let Some(pending_line) = self.pending_line.replace(line) {
+            self.write(&pending_line);
+        }
+    }
+
+    fn write_pending_line(&mut self) {
+        if let Some(pending_line) = self.pending_line.take() {
+            self.write(&pending_line);
+        }
+    }
+
+    /// writes the findings as ProVerif comments, so the annotated log stays readable by other tools
+    pub fn write_findings(&mut self, iteration: usize, findings: &[(Severity, &String, &String)]) {
+        if !self.annotate {
+            return;
+        }
+
+        for (severity, header, line) in findings {
+            let comment = format!("(* proverif_terminator: iteration {iteration}: [{severity:?}] {header}: {line} *)\n");
+            self.write(comment.as_bytes());
+        }
+    }
+
+    /// writes the last line and flushes, returns the first error that occurred while writing
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_pending_line();
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        match self.writer {
+            TeeWriter::Plain(mut writer) => writer.flush(),
+            TeeWriter::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let result = match &mut self.writer {
+            TeeWriter::Plain(writer) => writer.write_all(bytes),
+            TeeWriter::Gzip(encoder) => encoder.write_all(bytes),
+        };
+        self.error = result.err();
+    }
+}
+
+#[test]
+fn test_tee() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let directory = std::env::temp_dir();
+    let header = "Hypothesis cycle".to_string();
+    let line = "size 2, repeat 60".to_string();
+
+    for extension in ["log", "log.gz"] {
+        let path = directory.join(format!("proverif_terminator_test_tee_{}.{extension}", std::process::id()));
+        let mut tee = Tee::create(&path, true).unwrap();
+        tee.write_line(b"1 rules inserted.\n".to_vec());
+        tee.write_line(b"Rule with hypothesis fact 0 selected: att2(x_1,x_1) (* \xe9 *)\n".to_vec());
+        tee.write_findings(1, &[(Severity::Warning, &header, &line)]);
+        tee.finish().unwrap();
+
+        // invalid UTF-8 is copied unchanged
+        let mut content = Vec::new();
+        if extension.ends_with("gz") {
+            GzDecoder::new(File::open(&path).unwrap()).read_to_end(&mut content).unwrap();
+        } else {
+            File::open(&path).unwrap().read_to_end(&mut content).unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            content,
+            b"1 rules inserted.\n(* proverif_terminator: iteration 1: [Warning] Hypothesis cycle: size 2, repeat 60 *)\nRule with hypothesis fact 0 selected: att2(x_1,x_1) (* \xe9 *)\n"
+        );
+    }
+}
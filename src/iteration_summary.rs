@@ -1,16 +1,30 @@
 use crate::printer::Printer;
+use crate::rule_explainer::{Ancestor, Link};
 use crate::saturation_state::Iteration;
+use crate::witness::{LoopFinding, LoopSpan};
 use crate::Cli;
+use clap::ValueEnum;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash, ValueEnum)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
 
 pub struct IterationSummary {
     selected_fact: String,
     query: String,
     new_queue_entries: Vec<String>,
-    ancestry: Option<Vec<Iteration>>,
+    ancestry: Option<Vec<Ancestor>>,
+    ancestry_root: Option<String>,
+    selected_rule: Option<String>,
     info: Vec<(String, String)>,
     warning: Vec<(String, String)>,
     error: Vec<(String, String)>,
     progress: String,
+    columns: Vec<String>,
+    loops: Vec<LoopFinding>,
 }
 
 impl IterationSummary {
@@ -20,15 +34,34 @@ impl IterationSummary {
             query,
             new_queue_entries,
             ancestry: None,
+            ancestry_root: None,
+            selected_rule: None,
             info: vec![],
             warning: vec![],
             error: vec![],
             progress,
+            columns: vec![],
+            loops: vec![],
         }
     }
 
-    pub(crate) fn add_ancestry(&mut self, ancestry: Vec<Iteration>) {
-        self.ancestry = Some(ancestry)
+    #[cfg(test)]
+    pub fn for_test() -> Self {
+        Self::new(String::new(), String::new(), vec![], String::new())
+    }
+
+    /// `root` describes where the derivation starts, e.g. the initial clause
+    pub(crate) fn add_ancestry(&mut self, ancestry: Vec<Ancestor>, root: Option<String>) {
+        self.ancestry = Some(ancestry);
+        self.ancestry_root = root;
+    }
+
+    pub fn add_selected_rule(&mut self, selected_rule: String) {
+        self.selected_rule = Some(selected_rule);
+    }
+
+    pub fn add_column(&mut self, column: String) {
+        self.columns.push(column);
     }
 
     pub fn add_info(&mut self, header: String, line: String) {
@@ -40,13 +73,50 @@ impl IterationSummary {
     pub fn add_error(&mut self, header: String, line: String) {
         self.error.push((header, line));
     }
+    pub fn add_finding(&mut self, severity: Severity, header: String, line: String) {
+        match severity {
+            Severity::Info => self.add_info(header, line),
+            Severity::Warning => self.add_warning(header, line),
+            Severity::Error => self.add_error(header, line),
+        }
+    }
+
+    /// a finding about a loop, whose witness can be extracted from the iterations
+    pub fn add_loop(&mut self, header: String, description: String, span: LoopSpan) {
+        self.loops.push(LoopFinding { header, description, span });
+    }
+
+    pub fn loops(&self) -> &[LoopFinding] {
+        &self.loops
+    }
+
+    pub fn findings(&self) -> Vec<(Severity, &String, &String)> {
+        let info = self.info.iter().map(|(header, line)| (Severity::Info, header, line));
+        let warning = self.warning.iter().map(|(header, line)| (Severity::Warning, header, line));
+        let error = self.error.iter().map(|(header, line)| (Severity::Error, header, line));
+
+        info.chain(warning).chain(error).collect()
+    }
+
+    pub fn retain_findings(&mut self, mut keep: impl FnMut(Severity, &String, &String) -> bool) {
+        self.info.retain(|(header, line)| keep(Severity::Info, header, line));
+        self.warning.retain(|(header, line)| keep(Severity::Warning, header, line));
+        self.error.retain(|(header, line)| keep(Severity::Error, header, line));
+    }
+
+    /// whether the iteration is printed as a single line (i.e. there is nothing to describe in detail)
+    pub fn is_condensed(&self, cli: &Cli) -> bool {
+        let description = self.describe_iteration(cli);
+
+        description.len() + self.info.len() + self.warning.len() + self.error.len() == 0
+    }
 
     pub fn print(&self, cli: &Cli, printer: &Printer) {
         let description = self.describe_iteration(cli);
+        let progress = self.describe_progress();
 
-        let total_output = description.len() + self.info.len() + self.warning.len() + self.error.len();
-        if total_output == 0 {
-            printer.print(&format!("{}\tSelected: {}", &self.progress, &self.selected_fact));
+        if self.is_condensed(cli) {
+            printer.print(&format!("{}\tSelected: {}", progress, &self.selected_fact));
             return;
         }
 
@@ -65,23 +135,41 @@ impl IterationSummary {
             printer.print_error(header, line)
         }
 
-        printer.print(&format!("Total: {}", self.progress));
+        printer.print(&format!("Total: {}", progress));
         printer.print_group_separator();
     }
 
+    fn describe_progress(&self) -> String {
+        let mut progress = self.progress.clone();
+        for column in self.columns.iter() {
+            progress = format!("{progress}\t{column}");
+        }
+
+        progress
+    }
+
     fn describe_iteration(&self, cli: &Cli) -> Vec<String> {
         let all = cli.all || cli.print_all;
 
         let mut description = Vec::new();
         if let Some(ancestry) = self.ancestry.clone() {
             description.push("Ancestors:".to_string());
+            if let Some(root) = &self.ancestry_root {
+                description.push(format!("- {root}"));
+            }
             for ancestor in ancestry.iter().rev() {
-                let iteration_description = Self::describe_selected_iteration(ancestor);
+                let mut iteration_description = Self::describe_selected_iteration(&ancestor.iteration);
+                if !matches!(ancestor.link, Link::Selected | Link::Exact) {
+                    iteration_description = format!("{iteration_description}\t({}, confidence {:.1})", ancestor.link, ancestor.link.confidence());
+                }
                 description.push(format!("- {}", iteration_description));
             }
         }
         if all || cli.print_query {
             description.push(format!("Query: {}", &self.query));
+            if let Some(selected_rule) = &self.selected_rule {
+                description.push(format!("Queued as {selected_rule}"));
+            }
         }
         if all || cli.print_new_queue_entries {
             for queue_entry in self.new_queue_entries.iter() {
@@ -0,0 +1,444 @@
+use crate::clause::Clause;
+use regex::Regex;
+
+/// what a line (or a pair of lines) of the ProVerif log means for the saturation
+#[derive(Debug, PartialEq, Eq)]
+pub enum LogEvent {
+    /// e.g. "Query not attacker(s[]) in process 0", starts a new saturation
+    QueryHeader(String),
+    Completing,
+    StartingQuery,
+    Result(String),
+    /// a fact was selected, hence the previous iteration is complete; the selected clause follows on the next line
+    SelectionStarted,
+    InitialClause(Option<usize>, String),
+    QueueEntry(usize, String),
+    Progress {
+        iteration: usize,
+        with_conclusion_selected: usize,
+        with_hypothesis_selected: usize,
+        in_queue: usize,
+    },
+    HypothesisSelected {
+        fact: String,
+        fact_number: usize,
+        query: String,
+    },
+    ConclusionSelected {
+        fact: String,
+        query: String,
+    },
+    Malformed(MalformedLine),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MalformedLine {
+    pub line_number: usize,
+    pub reason: String,
+}
+
+enum RuleContext {
+    Unknown,
+    Initial,
+    Conclusion,
+    Hypothesis,
+    /// after a selection, before the queue is printed
+    Queue,
+    /// after "*** Rules in queue", every line is a queue entry
+    QueueDump,
+}
+
+enum Selection {
+    Hypothesis { fact: String, fact_number: usize },
+    Conclusion,
+}
+
+// a record spanning more lines is most likely not a clause, but e.g. the pretty printed process
+const MAX_RECORD_LINES: usize = 100;
+
+/// turns the log into events, line by line
+///
+/// Long clauses might be wrapped, hence lines with unbalanced brackets or ending in `->` or `&&` are joined with the following lines
+/// into one record, unless the following line starts a record on its own.
+///
+/// The selected clause (the "query") is printed on the line after the selected fact, hence the parser waits for it.
+/// If a line which is not a clause shows up instead, the selection is reported as malformed and the line is parsed as usual,
+/// so the parser resynchronizes at the next recognizable line.
+pub struct LogParser {
+    hypothesis_match: Regex,
+    conclusion_match: Regex,
+    conclusion_fact_match: Regex,
+    progress_match: Regex,
+
+    initial_start_match: Regex,
+    conclusion_start_match: Regex,
+    hypothesis_start_match: Regex,
+    queue_start_match: Regex,
+    query_header_match: Regex,
+    completing_match: Regex,
+    starting_query_match: Regex,
+    result_match: Regex,
+    rule_match: Regex,
+
+    rule_context: RuleContext,
+    line_number: usize,
+    // first line number and text of a record which continues on the next line
+    record: Option<(usize, String)>,
+    record_line_number: usize,
+    pending_selection: Option<(usize, Selection)>,
+
+    pub malformed_lines: usize,
+}
+
+impl LogParser {
+    pub fn new() -> Self {
+        LogParser {
+            hypothesis_match: Regex::new(r"Rule with hypothesis fact (?<fact_number>[0-9]+) selected: (?<fact>.+)").unwrap(),
+            conclusion_match: Regex::new(r"Rule with conclusion selected:").unwrap(),
+            conclusion_fact_match: Regex::new(r".+ -> (?<fact>.+)").unwrap(),
+            progress_match: Regex::new(r"(?<rules_inserted_count>\d+) rules inserted\. Base: (?<rules_base_count>\d+) rules \((?<rules_conclusion_selected_count>\d+) with conclusion selected\)\. Queue: (?<rules_queue_count>\d+) rules\.").unwrap(),
+
+            initial_start_match: Regex::new(r"^Starting rules").unwrap(),
+            conclusion_start_match: Regex::new(r"\*\*\* Rules with the conclusion selected").unwrap(),
+            hypothesis_start_match: Regex::new(r"\*\*\* Rules with an hypothesis selected").unwrap(),
+            queue_start_match: Regex::new(r"\*\*\* Rules in queue").unwrap(),
+            query_header_match: Regex::new(r"^-- (?<description>.+?)\.?\s*$").unwrap(),
+            completing_match: Regex::new(r"^Completing\.\.\.").unwrap(),
+            starting_query_match: Regex::new(r"^Starting query ").unwrap(),
+            result_match: Regex::new(r"^RESULT .+").unwrap(),
+            rule_match: Regex::new(r"(?<rule_number>[0-9]+) -- (?<rule>.+)").unwrap(),
+
+            rule_context: RuleContext::Unknown,
+            line_number: 0,
+            record: None,
+            record_line_number: 0,
+            pending_selection: None,
+
+            malformed_lines: 0,
+        }
+    }
+
+    pub fn parse_line(&mut self, line: &str) -> Vec<LogEvent> {
+        self.line_number += 1;
+        let line = line.trim_end_matches(['\n', '\r']);
+        let mut events = Vec::new();
+
+        if let Some((record_line_number, record)) = self.record.take() {
+            if !self.starts_record(line) && !self.is_truncated_selection(&record, line) {
+                self.assemble(record_line_number, format!("{} {}", record.trim_end(), line.trim()), &mut events);
+                return events;
+            }
+
+            // the record was not complete after all, parse it as is
+            events.extend(self.parse_record(record_line_number, &record));
+        }
+
+        self.assemble(self.line_number, line.to_string(), &mut events);
+        events
+    }
+
+    /// parses the last record, and reports a selection whose clause is missing because the input ended
+    pub fn finish(&mut self) -> Vec<LogEvent> {
+        let mut events = Vec::new();
+        if let Some((record_line_number, record)) = self.record.take() {
+            events.extend(self.parse_record(record_line_number, &record));
+        }
+
+        if let Some((selection_line_number, _)) = self.pending_selection.take() {
+            events.push(self.malformed(selection_line_number, "the input ended before the selected clause".to_string()));
+        }
+
+        events
+    }
+
+    /// waits for the next line if the record is incomplete, else parses it
+    fn assemble(&mut self, record_line_number: usize, record: String, events: &mut Vec<LogEvent>) {
+        let record_lines = self.line_number - record_line_number + 1;
+        if is_incomplete(&record) && record_lines < MAX_RECORD_LINES {
+            self.record = Some((record_line_number, record));
+            return;
+        }
+
+        events.extend(self.parse_record(record_line_number, &record));
+    }
+
+    fn parse_record(&mut self, record_line_number: usize, line: &str) -> Vec<LogEvent> {
+        self.record_line_number = record_line_number;
+        let mut events = Vec::new();
+
+        if let Some((selection_line_number, selection)) = self.pending_selection.take() {
+            if !line.trim().is_empty() && !self.is_marker(line) {
+                events.push(self.complete_selection(selection, line.trim()));
+                return events;
+            }
+
+            let reason = "no selected clause after the selected fact, the selection is skipped".to_string();
+            events.push(self.malformed(selection_line_number, reason));
+        }
+
+        if let Some(event) = self.parse_marker(line) {
+            events.push(event);
+        }
+
+        events
+    }
+
+    fn is_marker(&self, line: &str) -> bool {
+        let markers = [&self.hypothesis_match, &self.conclusion_match, &self.progress_match, &self.query_header_match, &self.starting_query_match, &self.result_match];
+        markers.iter().any(|marker| marker.is_match(line)) || line.starts_with("***")
+    }
+
+    /// whether the line cannot continue a record spanning multiple lines
+    fn starts_record(&self, line: &str) -> bool {
+        let markers = [&self.rule_match, &self.initial_start_match, &self.completing_match];
+        self.is_marker(line) || markers.iter().any(|marker| marker.is_match(line))
+    }
+
+    /// a selected fact with unbalanced brackets (e.g. cut off) must not swallow the selected clause on the next line
+    fn is_truncated_selection(&self, record: &str, line: &str) -> bool {
+        self.hypothesis_match.is_match(record) && !is_incomplete(line) && Clause::parse(line.trim()).is_some_and(|clause| !clause.hypotheses.is_empty())
+    }
+
+    fn parse_marker(&mut self, line: &str) -> Option<LogEvent> {
+        if let Some(query_header_capture) = self.query_header_match.captures(line) {
+            self.rule_context = RuleContext::Unknown;
+            return Some(LogEvent::QueryHeader(query_header_capture["description"].to_string()));
+        }
+
+        if self.completing_match.is_match(line) {
+            return Some(LogEvent::Completing);
+        }
+
+        if self.starting_query_match.is_match(line) {
+            self.rule_context = RuleContext::Unknown;
+            return Some(LogEvent::StartingQuery);
+        }
+
+        if let Some(result_capture) = self.result_match.find(line) {
+            return Some(LogEvent::Result(result_capture.as_str().trim().to_string()));
+        }
+
+        if let Some(rule_capture) = self.rule_match.captures(line) {
+            let rule = rule_capture["rule"].trim().to_string();
+            let Ok(rule_number) = rule_capture["rule_number"].parse::<usize>() else {
+                return Some(self.malformed(self.record_line_number, "rule number out of range".to_string()));
+            };
+
+            return match self.rule_context {
+                RuleContext::Queue | RuleContext::QueueDump => Some(LogEvent::QueueEntry(rule_number, rule)),
+                RuleContext::Initial => Some(LogEvent::InitialClause(Some(rule_number), rule)),
+                _ => None,
+            };
+        }
+
+        if self.initial_start_match.is_match(line) {
+            self.rule_context = RuleContext::Initial;
+            return None;
+        }
+
+        // initial clauses might not be numbered, the first line which is not a clause ends them
+        if let RuleContext::Initial = self.rule_context {
+            if line.trim().is_empty() {
+                return None;
+            }
+            if Clause::parse(line.trim()).is_some() {
+                return Some(LogEvent::InitialClause(None, line.trim().to_string()));
+            }
+            self.rule_context = RuleContext::Unknown;
+        }
+
+        if self.conclusion_start_match.is_match(line) {
+            self.rule_context = RuleContext::Conclusion;
+            return None;
+        }
+
+        if self.hypothesis_start_match.is_match(line) {
+            self.rule_context = RuleContext::Hypothesis;
+            return None;
+        }
+
+        if self.queue_start_match.is_match(line) {
+            self.rule_context = RuleContext::QueueDump;
+            return None;
+        }
+
+        if let Some(progress_capture) = self.progress_match.captures(line) {
+            let count = |name: &str| progress_capture[name].parse::<usize>().ok();
+            let counts = (count("rules_inserted_count"), count("rules_base_count"), count("rules_conclusion_selected_count"), count("rules_queue_count"));
+            return match counts {
+                (Some(iteration), Some(base), Some(with_conclusion_selected), Some(in_queue)) if with_conclusion_selected <= base => Some(LogEvent::Progress {
+                    iteration,
+                    with_conclusion_selected,
+                    with_hypothesis_selected: base - with_conclusion_selected,
+                    in_queue,
+                }),
+                _ => Some(self.malformed(self.record_line_number, "inconsistent rule counts".to_string())),
+            };
+        }
+
+        if let Some(hypothesis_capture) = self.hypothesis_match.captures(line) {
+            self.rule_context = RuleContext::Queue;
+            let fact = hypothesis_capture["fact"].to_string();
+            let Ok(fact_number) = hypothesis_capture["fact_number"].parse::<usize>() else {
+                return Some(self.malformed(self.record_line_number, "fact number out of range".to_string()));
+            };
+
+            self.pending_selection = Some((self.record_line_number, Selection::Hypothesis { fact, fact_number }));
+            return Some(LogEvent::SelectionStarted);
+        }
+
+        if self.conclusion_match.is_match(line) {
+            self.rule_context = RuleContext::Queue;
+            self.pending_selection = Some((self.record_line_number, Selection::Conclusion));
+            return Some(LogEvent::SelectionStarted);
+        }
+
+        // looks like a line we know, but does not have the expected format
+        if line.starts_with("Rule with") || line.contains("rules inserted.") {
+            return Some(self.malformed(self.record_line_number, "unrecognized format".to_string()));
+        }
+
+        // the dump ends with the next marker or an empty line
+        if let RuleContext::QueueDump = self.rule_context {
+            if line.trim().is_empty() {
+                self.rule_context = RuleContext::Queue;
+                return None;
+            }
+            return Some(self.malformed(self.record_line_number, "not a queue entry".to_string()));
+        }
+
+        None
+    }
+
+    fn complete_selection(&self, selection: Selection, query: &str) -> LogEvent {
+        let query = query.to_string();
+        match selection {
+            Selection::Hypothesis { fact, fact_number } => LogEvent::HypothesisSelected { fact, fact_number, query },
+            Selection::Conclusion => {
+                let fact = match self.conclusion_fact_match.captures(&query) {
+                    Some(conclusion_fact_capture) => conclusion_fact_capture["fact"].to_string(),
+                    None => query.clone(),
+                };
+                LogEvent::ConclusionSelected { fact, query }
+            }
+        }
+    }
+
+    fn malformed(&mut self, line_number: usize, reason: String) -> LogEvent {
+        self.malformed_lines += 1;
+        LogEvent::Malformed(MalformedLine { line_number, reason })
+    }
+}
+
+/// whether the record continues on the next line
+fn is_incomplete(record: &str) -> bool {
+    let mut depth: isize = 0;
+    for char in record.chars() {
+        match char {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    let record = record.trim_end();
+    depth > 0 || record.ends_with("->") || record.ends_with("&&")
+}
+
+#[test]
+fn test_resynchronization() {
+    let mut parser = LogParser::new();
+    let log = [
+        "Rule with hypothesis fact 0 selected: att2(x_1,y_1)",
+        "Rule with hypothesis fact 1 selected: mess2(c[],x_1)",
+        "mess2(c[],x_1) && att2(x_1,x_1) -> att2(f(x_1),x_1)",
+        "3 rules inserted. Base: 2 rules (3 with conclusion selected). Queue: 1 rules.",
+        "Rule with conclusion selected:",
+    ];
+    let events: Vec<LogEvent> = log.iter().flat_map(|line| parser.parse_line(line)).collect();
+
+    let malformed = |line_number: usize, reason: &str| LogEvent::Malformed(MalformedLine { line_number, reason: reason.to_string() });
+    assert_eq!(
+        events,
+        vec![
+            LogEvent::SelectionStarted,
+            malformed(1, "no selected clause after the selected fact, the selection is skipped"),
+            LogEvent::SelectionStarted,
+            LogEvent::HypothesisSelected {
+                fact: "mess2(c[],x_1)".to_string(),
+                fact_number: 1,
+                query: "mess2(c[],x_1) && att2(x_1,x_1) -> att2(f(x_1),x_1)".to_string()
+            },
+            malformed(4, "inconsistent rule counts"),
+            LogEvent::SelectionStarted,
+        ]
+    );
+    assert_eq!(parser.finish(), vec![malformed(5, "the input ended before the selected clause")]);
+    assert_eq!(parser.malformed_lines, 3);
+}
+
+#[test]
+fn test_multi_line_records() {
+    let mut parser = LogParser::new();
+    let log = [
+        "Rule with hypothesis fact 1 selected: att2(choice[x_1,",
+        "    y_1],z_1)",
+        "mess2(c[],(x_1,x_2,",
+        "    x_3,x_4)) &&",
+        "  att2(choice[x_1,y_1],z_1) ->",
+        "  att2((x_1,x_2,x_3,x_4),z_1)",
+        "*** Rules in queue",
+        "0 -- att2(x_1,",
+        "1 -- att2(x_1,x_1)",
+    ];
+    let mut events: Vec<LogEvent> = log.iter().flat_map(|line| parser.parse_line(line)).collect();
+    events.extend(parser.finish());
+
+    assert_eq!(
+        events,
+        vec![
+            LogEvent::SelectionStarted,
+            LogEvent::HypothesisSelected {
+                fact: "att2(choice[x_1, y_1],z_1)".to_string(),
+                fact_number: 1,
+                query: "mess2(c[],(x_1,x_2, x_3,x_4)) && att2(choice[x_1,y_1],z_1) -> att2((x_1,x_2,x_3,x_4),z_1)".to_string()
+            },
+            LogEvent::QueueEntry(0, "att2(x_1,".to_string()),
+            LogEvent::QueueEntry(1, "att2(x_1,x_1)".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_truncated_selection() {
+    let mut parser = LogParser::new();
+    let log = [
+        "Rule with hypothesis fact 0 selected: att2(f(f(x_1)),x_1",
+        "att2(f(f(x_1)),x_1) -> att2(f(f(f(x_1))),x_1)",
+        "*** Rules in queue",
+        "0 -- att2(x_1,x_1)",
+        "garbage -- this is not a queue entry",
+        "",
+        "some other line",
+    ];
+    let mut events: Vec<LogEvent> = log.iter().flat_map(|line| parser.parse_line(line)).collect();
+    events.extend(parser.finish());
+
+    assert_eq!(
+        events,
+        vec![
+            LogEvent::SelectionStarted,
+            LogEvent::HypothesisSelected {
+                fact: "att2(f(f(x_1)),x_1".to_string(),
+                fact_number: 0,
+                query: "att2(f(f(x_1)),x_1) -> att2(f(f(f(x_1))),x_1)".to_string()
+            },
+            LogEvent::QueueEntry(0, "att2(x_1,x_1)".to_string()),
+            LogEvent::Malformed(MalformedLine {
+                line_number: 5,
+                reason: "not a queue entry".to_string()
+            }),
+        ]
+    );
+    assert_eq!(parser.malformed_lines, 1);
+}
@@ -1,40 +1,183 @@
+mod bi_fact_checker;
+mod clause;
+mod clause_tracker;
+mod counter_checker;
+mod cycle_search;
 mod cycles;
+mod derivation_depth_checker;
+mod detectors;
 mod fact_checker;
+#[cfg(test)]
+mod history_generators;
+mod html_report;
+mod input;
 mod iteration_summary;
+mod log_parser;
+mod metrics_export;
+mod output;
 mod printer;
+mod queue_checker;
+mod replication_checker;
+mod report_policy;
 mod rule_explainer;
 mod saturation_state;
-
-use crate::cycles::CycleDetector;
-use crate::fact_checker::FactChecker;
-use crate::printer::Printer;
+mod status_bar;
+mod term;
+mod term_metrics_checker;
+mod witness;
+
+use crate::cycles::CycleSignal;
+use crate::detectors::Detectors;
+use crate::input::{Input, Tee};
+use crate::iteration_summary::Severity;
+use crate::log_parser::{LogEvent, LogParser};
+use crate::output::{create_file, Output};
+use crate::printer::ColorChoice;
+use crate::report_policy::{parse_keyed_thresholds, ReportPolicy, Thresholds};
 use crate::rule_explainer::QueryExplainer;
-use crate::saturation_state::SaturationState;
+use crate::saturation_state::{SaturationPhase, SaturationState};
 use clap::Parser;
-use regex::Regex;
-use std::io::{self};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 struct Cli {
+    /// prints all the details, and detects high counters
     #[arg(short, long)]
     all: bool,
 
+    /// enables all detectors
     #[arg(short, long)]
     detect_all: bool,
+    /// names where a two-digit integer is in the selected hypothesis: the channel, message or table entry of `mess`, `att`, `table`, `input`, `output`, the argument of a user event, or the argument position of custom predicates
     #[arg(long)]
     detect_high_counters: bool,
+    /// follows every integer literal of the selected facts by its position (e.g. `mess2.1.0`) and warns when it increases several times in a row, before it reaches two digits
+    #[arg(long)]
+    detect_counters: bool,
+    /// reports repeating sequences in the signals of `--cycle-signal`
     #[arg(long)]
     detect_cycles: bool,
-
+    /// the part of the iterations in which cycles are searched, can be given several times
+    #[arg(long = "cycle-signal", value_enum, default_values_t = [CycleSignal::Hypothesis])]
+    cycle_signals: Vec<CycleSignal>,
+    #[arg(long, default_value_t = 1000)]
+    max_cycle_size: usize,
+    /// the number of iterations a cycle spans before it is reported as `<info>,<warning>,<error>`
+    #[arg(long, default_value = "10,100,1000")]
+    cycle_thresholds: Thresholds,
+    /// overrides the cycle thresholds of one signal, as `<signal>=<info>,<warning>,<error>`
+    #[arg(long, value_parser = parse_keyed_thresholds)]
+    detector_cycle_thresholds: Vec<(String, Thresholds)>,
+    /// overrides the cycle thresholds of cycles through one predicate, as `<predicate>=<info>,<warning>,<error>`
+    #[arg(long, value_parser = parse_keyed_thresholds)]
+    predicate_cycle_thresholds: Vec<(String, Thresholds)>,
+    /// reports selected facts exceeding the `--max-term-*` limits
+    #[arg(long)]
+    detect_term_metrics: bool,
+    /// warns when only one side of a bi-fact (`att2`, `mess2`, `table2`, `input2`, `output2`) keeps growing
+    #[arg(long)]
+    detect_bi_facts: bool,
+    /// additionally runs the cycle and term metric detectors on each side of the biprocess separately
+    #[arg(long)]
+    per_side: bool,
+    /// flags clauses queued again after an equal clause, growing backlogs of clauses concluding one predicate, and the first selection which is not the head of the queue
+    #[arg(long)]
+    detect_queue: bool,
+    /// how often an equal clause is queued again before it is reported
+    #[arg(long, default_value = "3,10,100")]
+    requeue_thresholds: Thresholds,
+    /// how many queued clauses of one predicate are reported, again whenever the backlog doubles
+    #[arg(long, default_value = "1000,10000,100000")]
+    backlog_thresholds: Thresholds,
+    /// reports ever deeper derivations, counting one resolution step per queued clause from the initial clauses (or the first queue dump)
+    #[arg(long)]
+    detect_derivation_depth: bool,
+    /// warns when the number of session indices or nested replication markers in the selected fact keeps increasing
+    #[arg(long)]
+    detect_replication: bool,
+    #[arg(long, default_value = "20,50,100")]
+    derivation_depth_thresholds: Thresholds,
+
+    #[arg(long, default_value_t = 8)]
+    max_term_depth: usize,
+    #[arg(long, default_value_t = 100)]
+    max_term_size: usize,
+    #[arg(long, default_value_t = 20)]
+    max_term_variables: usize,
+    #[arg(long, default_value_t = 10)]
+    max_session_names: usize,
+
+    /// prints all the columns and details below
     #[arg(short, long)]
     print_all: bool,
+    /// prints the selected clause, and which queued clause it is and how long it waited
     #[arg(long)]
     print_query: bool,
     #[arg(long)]
     print_new_queue_entries: bool,
+    /// adds the depth, size, variables and session names of the selected fact as a column
+    #[arg(long)]
+    print_term_metrics: bool,
+    /// adds a `depth N` column
+    #[arg(long)]
+    print_derivation_depth: bool,
 
+    /// explains the clause selected in the given iteration of each query, counted from 1 within the query (as the iteration numbers restart per query)
     #[arg(long)]
     explain_query: Option<usize>,
+
+    /// reports every finding only once
+    #[arg(long)]
+    dedupe_findings: bool,
+    /// reports a finding again only after the given number of iterations, unless its severity increased
+    #[arg(long)]
+    finding_interval: Option<usize>,
+    /// exits with a non-zero code once the input ends if a finding of this severity was found (output files which cannot be created exit with code 2 before reading the log)
+    #[arg(long, value_enum)]
+    fail_on: Option<Severity>,
+
+    /// writes a self-contained HTML page (queue chart, predicates, findings with ancestry, selected facts) once the input ends
+    #[arg(long)]
+    html_report: Option<PathBuf>,
+    /// writes one CSV row per iteration, for plotting
+    #[arg(long)]
+    metrics_out: Option<PathBuf>,
+    /// writes every loop found (one period of a cycle, or a growing derivation) as canonical selected facts and clauses, once
+    #[arg(long)]
+    witness_out: Option<PathBuf>,
+    /// copies the raw log while analysing it, gzip-compressed if the name ends in `.gz`
+    #[arg(long)]
+    tee: Option<PathBuf>,
+    /// interleaves the findings with the copied log as `(* proverif_terminator: ... *)` comments
+    #[arg(long)]
+    tee_findings: bool,
+
+    /// colors only terminals by default, and honors `NO_COLOR`
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// renders findings as Markdown list entries
+    #[arg(long)]
+    markdown: bool,
+
+    /// hides the condensed iterations, i.e. those without findings or details; detectors still see every iteration
+    #[arg(short, long)]
+    quiet: bool,
+    /// prints only every n-th condensed iteration
+    #[arg(long)]
+    every: Option<usize>,
+    /// prints only iterations selecting a fact of this predicate, can be given several times
+    #[arg(long = "only-predicate")]
+    only_predicates: Vec<String>,
+    /// prints only findings of at least this severity
+    #[arg(long, value_enum)]
+    min_severity: Option<Severity>,
+
+    /// redraws a footer with the iteration, queue and base sizes, speed, a queue sparkline, the dominant predicate and the last finding (only on a terminal, and not with `--color never`)
+    #[arg(long)]
+    status: bool,
+    /// shows the footer without the findings above it
+    #[arg(long)]
+    status_only: bool,
 }
 
 fn main() {
@@ -42,139 +185,116 @@ fn main() {
 
     let mut saturation_state = SaturationState::new();
 
-    let fact_checker = FactChecker::new(&cli);
-    let mut cycle_detector = CycleDetector::new();
+    let mut detectors = Detectors::new(&cli);
 
-    let printer = Printer::new();
+    let mut report_policy = ReportPolicy::new(&cli);
+    let mut output = Output::new(&cli);
 
-    let stdin = io::stdin();
+    let tee = cli.tee.as_ref().map(|path| create_file(&output.printer, path, |path| Tee::create(path, cli.tee_findings)));
+    let mut input = Input::new(tee);
 
-    let hypothesis_match = Regex::new(r"Rule with hypothesis fact (?<fact_number>[0-9]+) selected: (?<fact>.+)").unwrap();
-    let conclusion_match = Regex::new(r"Rule with conclusion selected:").unwrap();
-    let conclusion_fact_match = Regex::new(r".+ -> (?<fact>.+)").unwrap();
-    let progress_match = Regex::new(r"(?<rules_inserted_count>\d+) rules inserted\. Base: (?<rules_base_count>\d+) rules \((?<rules_conclusion_selected_count>\d+) with conclusion selected\)\. Queue: (?<rules_queue_count>\d+) rules\.").unwrap();
+    let mut log_parser = LogParser::new();
 
-    let conclusion_start_match = Regex::new(r"\*\*\* Rules with the conclusion selected").unwrap();
-    let hypothesis_start_match = Regex::new(r"\*\*\* Rules with an hypothesis selected").unwrap();
-    let queue_start_match = Regex::new(r"\*\*\* Rules in queue").unwrap();
-    let rule_match = Regex::new(r"(?<rule_number>[0-9]+) -- (?<rule>.+)").unwrap();
-    let mut rule_context = RuleContext::Unknown;
+    let mut input_ended = false;
+    while !input_ended {
+        let events = match input.read_line() {
+            Some(line) => log_parser.parse_line(&line),
+            None => {
+                input_ended = true;
+                log_parser.finish()
+            }
+        };
 
-    loop {
-        let mut line = String::new();
-        stdin.read_line(&mut line).unwrap();
+        for event in events {
+            match event {
+                LogEvent::QueryHeader(description) => {
+                    flush_iteration(&cli, &mut saturation_state, &mut detectors, &mut report_policy, &mut output, &mut input);
 
-        if let Some(rule_capture) = rule_match.captures(&line) {
-            match rule_context {
-                RuleContext::Queue => {}
-                _ => {
-                    continue;
+                    output.print_lines(&["".to_string(), description.clone()]);
+                    saturation_state.start_saturation(description);
+                    detectors = Detectors::new(&cli);
+                    report_policy.start_saturation();
+                }
+                LogEvent::Completing => saturation_state.set_saturation_phase(SaturationPhase::Completing),
+                LogEvent::StartingQuery => {
+                    // the saturation is done, so the last iteration is complete
+                    flush_iteration(&cli, &mut saturation_state, &mut detectors, &mut report_policy, &mut output, &mut input);
+                    saturation_state.set_saturation_phase(SaturationPhase::Completed);
+                }
+                LogEvent::Result(result) => {
+                    output.print_lines(std::slice::from_ref(&result));
+                    saturation_state.add_result(result);
                 }
+                LogEvent::InitialClause(rule_number, rule) => {
+                    saturation_state.add_initial_clause(rule_number, &rule);
+                }
+                LogEvent::QueueEntry(rule_number, rule) => saturation_state.set_queue_entry(rule_number, rule),
+                LogEvent::Progress {
+                    iteration,
+                    with_conclusion_selected,
+                    with_hypothesis_selected,
+                    in_queue,
+                } => saturation_state.set_saturation_progress(iteration, with_conclusion_selected, with_hypothesis_selected, in_queue),
+                LogEvent::SelectionStarted => flush_iteration(&cli, &mut saturation_state, &mut detectors, &mut report_policy, &mut output, &mut input),
+                LogEvent::HypothesisSelected { fact, fact_number, query } => {
+                    saturation_state.set_query(query);
+                    saturation_state.set_hypothesis_fact_selected(fact, fact_number);
+                }
+                LogEvent::ConclusionSelected { fact, query } => {
+                    saturation_state.set_query(query);
+                    saturation_state.set_conclusion_fact_selected(fact);
+                }
+                LogEvent::Malformed(malformed_line) => output.print_malformed_line(&malformed_line),
             }
-
-            let rule = rule_capture.name("rule").unwrap().as_str();
-            let rule_number = rule_capture.name("rule_number").unwrap().as_str();
-            let rule_number = rule_number.parse::<usize>().unwrap_or(0);
-
-            saturation_state.set_queue_entry(rule_number, rule.to_string());
-
-            continue;
-        }
-
-        if conclusion_start_match.captures(&line).is_some() {
-            rule_context = RuleContext::Conclusion;
-        }
-
-        if hypothesis_start_match.captures(&line).is_some() {
-            rule_context = RuleContext::Hypothesis;
-        }
-
-        if queue_start_match.captures(&line).is_some() {
-            rule_context = RuleContext::Queue;
-        }
-
-        if let Some(progress_capture) = progress_match.captures(&line) {
-            let rules_inserted_count = progress_capture.name("rules_inserted_count").unwrap().as_str();
-            let rules_base_count = progress_capture.name("rules_base_count").unwrap().as_str();
-            let rules_conclusion_selected_count: &str = progress_capture.name("rules_conclusion_selected_count").unwrap().as_str();
-            let rules_queue_count: &str = progress_capture.name("rules_queue_count").unwrap().as_str();
-
-            let iteration = rules_inserted_count.parse::<usize>().unwrap_or(0);
-            let in_queue = rules_queue_count.parse::<usize>().unwrap_or(0);
-            let with_conclusion_selected = rules_conclusion_selected_count.parse::<usize>().unwrap_or(0);
-            let with_hypothesis_selected = rules_base_count.parse::<usize>().unwrap_or(0) - with_conclusion_selected;
-            saturation_state.set_saturation_progress(iteration, with_conclusion_selected, with_hypothesis_selected, in_queue);
-            continue;
-        }
-
-        if let Some(hypothesis_capture) = hypothesis_match.captures(&line) {
-            rule_context = RuleContext::Queue;
-            flush_iteration(&cli, &mut saturation_state, &fact_checker, &mut cycle_detector, &printer);
-
-            let mut query = String::new();
-            stdin.read_line(&mut query).unwrap();
-            saturation_state.set_query(query.clone().trim().to_string());
-
-            let fact = hypothesis_capture.name("fact").unwrap().as_str();
-            let fact_number = hypothesis_capture.name("fact_number").unwrap().as_str();
-
-            let fact_number = fact_number.parse::<usize>().unwrap_or(0);
-            saturation_state.set_hypothesis_fact_selected(fact.to_string(), fact_number);
-            continue;
         }
+    }
 
-        if conclusion_match.captures(&line).is_some() {
-            rule_context = RuleContext::Queue;
-            flush_iteration(&cli, &mut saturation_state, &fact_checker, &mut cycle_detector, &printer);
-
-            let mut query = String::new();
-            stdin.read_line(&mut query).unwrap();
-            saturation_state.set_query(query.clone().trim().to_string());
-
-            let conclusion_fact_capture = conclusion_fact_match.captures(&query);
-            if let Some(conclusion_fact_capture) = conclusion_fact_capture {
-                let fact = conclusion_fact_capture.name("fact").unwrap().as_str();
+    flush_iteration(&cli, &mut saturation_state, &mut detectors, &mut report_policy, &mut output, &mut input);
 
-                saturation_state.set_conclusion_fact_selected(fact.to_string());
-            } else {
-                saturation_state.set_conclusion_fact_selected(query.trim().to_string());
-            }
-            continue;
+    if let Some(error) = input.error.take() {
+        output.printer.print_error("Input", &format!("could not read the log: {error}"));
+    }
+    if let Some(tee) = input.tee.take() {
+        if let Err(error) = tee.finish() {
+            output.printer.print_error("Tee", &format!("could not write the log copy: {error}"));
         }
     }
-}
 
-enum RuleContext {
-    Unknown,
-    Conclusion,
-    Hypothesis,
-    Queue,
+    output.finish(&cli, &saturation_state, &report_policy, log_parser.malformed_lines);
+    std::process::exit(report_policy.exit_code());
 }
 
-fn flush_iteration(cli: &Cli, saturation_state: &mut SaturationState, fact_checker: &FactChecker, cycle_detector: &mut CycleDetector, printer: &Printer) {
+fn flush_iteration(cli: &Cli, saturation_state: &mut SaturationState, detectors: &mut Detectors, report_policy: &mut ReportPolicy, output: &mut Output, input: &mut Input) {
     let iteration_summary = saturation_state.complete_iteration();
     if iteration_summary.is_none() {
         return;
     }
 
     let mut iteration_summary = iteration_summary.unwrap();
+    let iteration = saturation_state.iterations.last().unwrap();
 
-    if cli.detect_all || cli.detect_cycles {
-        cycle_detector.check_cycles(&saturation_state.hypothesis_selected_fact_history, &mut iteration_summary);
-    }
-
-    if let Some(history) = saturation_state.hypothesis_selected_fact_history.last() {
-        fact_checker.check(&history.0, &mut iteration_summary)
-    }
+    detectors.check(cli, iteration, &saturation_state.current_saturation().clauses, &mut iteration_summary);
 
     if let Some(explain_query) = cli.explain_query {
-        if saturation_state.iterations.len() == explain_query {
-            let ancestry = QueryExplainer::get_ancestry(&saturation_state.iterations);
-            iteration_summary.add_ancestry(ancestry);
+        if saturation_state.current_iterations().len() == explain_query {
+            let initial_clauses = &saturation_state.current_saturation().initial_clauses;
+            let ancestry = QueryExplainer::get_ancestry(saturation_state.current_iterations(), initial_clauses);
+            let root = match QueryExplainer::get_root(&ancestry, initial_clauses) {
+                Some(initial_clause) => Some(initial_clause.to_string()),
+                None if !initial_clauses.is_empty() => Some("no matching initial clause".to_string()),
+                None => None,
+            };
+            iteration_summary.add_ancestry(ancestry, root);
         }
-        cycle_detector.check_cycles(&saturation_state.hypothesis_selected_fact_history, &mut iteration_summary);
     }
 
+    output.write_witnesses(saturation_state, &mut iteration_summary);
+
     // print
-    iteration_summary.print(cli, printer)
+    let finding_raised = !iteration_summary.findings().is_empty();
+    report_policy.apply(saturation_state.iterations.len() - 1, iteration.progress.iteration, &mut iteration_summary);
+    if let Some(tee) = input.tee.as_mut() {
+        tee.write_findings(iteration.progress.iteration, &iteration_summary.findings());
+    }
+    output.print_iteration(cli, saturation_state.saturations.len(), iteration, &mut iteration_summary, finding_raised);
 }
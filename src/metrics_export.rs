@@ -0,0 +1,78 @@
+use crate::saturation_state::Iteration;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// writes one CSV row per iteration, to plot the saturation without parsing the human-readable output
+pub struct MetricsExport {
+    writer: BufWriter<File>,
+}
+
+impl MetricsExport {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "query,iteration,with_conclusion_selected,with_hypothesis_selected,in_queue,selected,predicate,term_depth,new_queue_entries,finding")?;
+
+        Ok(MetricsExport { writer })
+    }
+
+    /// iteration numbers restart with every query, `query` numbers the saturation the iteration belongs to
+    pub fn write_iteration(&mut self, query: usize, iteration: &Iteration, finding: bool) -> std::io::Result<()> {
+        writeln!(self.writer, "{}", format_row(query, iteration, finding))
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn format_row(query: usize, iteration: &Iteration, finding: bool) -> String {
+    let progress = iteration.progress;
+    let selected = if iteration.hypothesis_fact_selected.is_some() { "hypothesis" } else { "conclusion" };
+    let predicate = iteration.selected_fact().and_then(|f| f.term.as_ref()).and_then(|t| t.predicate()).unwrap_or("");
+    let term_depth = iteration.term_metrics().map(|metrics| metrics.depth.to_string()).unwrap_or_default();
+
+    format!(
+        "{query},{},{},{},{},{selected},{},{term_depth},{},{}",
+        progress.iteration,
+        progress.with_conclusion_selected,
+        progress.with_hypothesis_selected,
+        progress.in_queue,
+        escape(predicate),
+        iteration.new_queue_entries.len(),
+        finding as u8
+    )
+}
+
+fn escape(value: &str) -> String {
+    if !value.contains([',', '"', '\n']) {
+        return value.to_string();
+    }
+
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[test]
+fn test_format_row() {
+    use crate::saturation_state::{QueueEntry, SaturationProgress, SelectedFact};
+    use crate::term::Term;
+
+    let fact = "mess2(c[],f(g(x_1)))".to_string();
+    let iteration = Iteration {
+        progress: SaturationProgress {
+            iteration: 12,
+            with_conclusion_selected: 3,
+            with_hypothesis_selected: 4,
+            in_queue: 5,
+        },
+        query: format!("{fact} -> att2(x_1,x_1)"),
+        hypothesis_fact_selected: Some(SelectedFact { term: Term::parse(&fact), fact, fact_number: Some(0) }),
+        conclusion_fact_selected: None,
+        new_queue_entries: vec![QueueEntry::new(3, "att2(x_1,x_1)".to_string())],
+        selected_rule_number: Some(2),
+        derivation_depth: Some(1),
+    };
+
+    assert_eq!(format_row(2, &iteration, true), "2,12,3,4,5,hypothesis,mess2,2,1,1");
+    assert_eq!(escape("a,b"), "\"a,b\"");
+}
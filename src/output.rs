@@ -0,0 +1,311 @@
+use crate::clause::ClauseKind;
+use crate::clause_tracker::ClauseTracker;
+use crate::html_report::HtmlReport;
+use crate::iteration_summary::{IterationSummary, Severity};
+use crate::log_parser::MalformedLine;
+use crate::metrics_export::MetricsExport;
+use crate::printer::{Printer, Theme};
+use crate::report_policy::{Finding, ReportPolicy};
+use crate::saturation_state::{Iteration, Saturation, SaturationPhase, SaturationState};
+use crate::status_bar::StatusBar;
+use crate::witness::WitnessWriter;
+use crate::Cli;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// decides which iterations are printed (detectors still see every iteration)
+pub struct OutputFilter {
+    quiet: bool,
+    every: Option<usize>,
+    predicates: Vec<String>,
+    min_severity: Option<Severity>,
+
+    condensed_iterations: usize,
+}
+
+impl OutputFilter {
+    pub fn new(cli: &Cli) -> Self {
+        OutputFilter {
+            quiet: cli.quiet,
+            every: cli.every,
+            predicates: cli.only_predicates.clone(),
+            min_severity: cli.min_severity,
+
+            condensed_iterations: 0,
+        }
+    }
+
+    /// removes findings below the minimum severity, and returns whether the iteration should be printed
+    pub fn apply(&mut self, cli: &Cli, iteration: &Iteration, iteration_summary: &mut IterationSummary) -> bool {
+        if let Some(min_severity) = self.min_severity {
+            iteration_summary.retain_findings(|severity, _, _| severity >= min_severity);
+        }
+
+        if !self.predicates.is_empty() {
+            let predicate = iteration.selected_fact().and_then(|f| f.term.as_ref()).and_then(|t| t.predicate());
+            if !predicate.is_some_and(|predicate| self.predicates.iter().any(|p| p == predicate)) {
+                return false;
+            }
+        }
+
+        if !iteration_summary.is_condensed(cli) {
+            return true;
+        }
+
+        if self.quiet {
+            return false;
+        }
+
+        self.condensed_iterations += 1;
+        match self.every {
+            Some(every) => (self.condensed_iterations - 1).is_multiple_of(every.max(1)),
+            None => true,
+        }
+    }
+}
+
+// a log which is not a ProVerif log at all should not flood the output
+const MAX_PRINTED_MALFORMED_LINES: usize = 10;
+
+/// exit code when an output file cannot be created, distinct from the one of `--fail-on`
+const CREATE_FILE_EXIT_CODE: i32 = 2;
+
+/// creates an output file before reading the log, and exits with an error if that fails
+pub fn create_file<T>(printer: &Printer, path: &Path, create: impl FnOnce(&Path) -> std::io::Result<T>) -> T {
+    create(path).unwrap_or_else(|error| {
+        printer.print_error("Output", &format!("could not create {}: {error}", path.display()));
+        std::process::exit(CREATE_FILE_EXIT_CODE);
+    })
+}
+
+/// everything written while and after processing the log
+pub struct Output {
+    pub printer: Printer,
+    printed_malformed_lines: usize,
+    filter: OutputFilter,
+    status_bar: Option<StatusBar>,
+    metrics_export: Option<MetricsExport>,
+    witness_writer: Option<WitnessWriter>,
+}
+
+impl Output {
+    pub fn new(cli: &Cli) -> Self {
+        let printer = Printer::new(Theme::detect(cli.color, cli.markdown));
+        let metrics_export = cli.metrics_out.as_ref().map(|path| create_file(&printer, path, MetricsExport::create));
+        let witness_writer = cli.witness_out.as_ref().map(|path| create_file(&printer, path, WitnessWriter::create));
+
+        Output {
+            printer,
+            printed_malformed_lines: 0,
+            filter: OutputFilter::new(cli),
+            status_bar: StatusBar::enabled(cli).then(StatusBar::new),
+            metrics_export,
+            witness_writer,
+        }
+    }
+
+    /// `query` numbers the saturation from 1, `finding_raised` refers to the findings of the detectors, before the report policy filtered them
+    pub fn print_iteration(&mut self, cli: &Cli, query: usize, iteration: &Iteration, iteration_summary: &mut IterationSummary, finding_raised: bool) {
+        if let Some(metrics_export) = self.metrics_export.as_mut() {
+            if let Err(error) = metrics_export.write_iteration(query, iteration, finding_raised) {
+                iteration_summary.add_error("Metrics".to_string(), format!("could not write metrics: {error}"));
+            }
+        }
+
+        let visible = self.filter.apply(cli, iteration, iteration_summary);
+        match self.status_bar.as_mut() {
+            Some(status_bar) => status_bar.print_iteration(cli, iteration, iteration_summary, visible, &self.printer),
+            None if visible => iteration_summary.print(cli, &self.printer),
+            None => {}
+        }
+    }
+
+    /// writes the witnesses of the loops found in the current iteration
+    pub fn write_witnesses(&mut self, saturation_state: &SaturationState, iteration_summary: &mut IterationSummary) {
+        let Some(witness_writer) = self.witness_writer.as_mut() else {
+            return;
+        };
+
+        let saturation = saturation_state.current_saturation();
+        let errors: Vec<String> = iteration_summary
+            .loops()
+            .iter()
+            .filter_map(|loop_finding| witness_writer.write(loop_finding, saturation_state.current_iterations(), saturation).err())
+            .map(|error| format!("could not write witness: {error}"))
+            .collect();
+        for error in errors {
+            iteration_summary.add_error("Witness".to_string(), error);
+        }
+    }
+
+    /// query headers and results, printed even in quiet mode
+    pub fn print_lines(&mut self, lines: &[String]) {
+        match self.status_bar.as_mut() {
+            Some(status_bar) => status_bar.print_lines(lines, &self.printer),
+            None => lines.iter().for_each(|line| self.printer.print(line)),
+        }
+    }
+
+    pub fn print_malformed_line(&mut self, malformed_line: &MalformedLine) {
+        self.printed_malformed_lines += 1;
+        let line = match self.printed_malformed_lines {
+            count if count < MAX_PRINTED_MALFORMED_LINES => format!("line {}: {}", malformed_line.line_number, malformed_line.reason),
+            MAX_PRINTED_MALFORMED_LINES => format!("line {}: {} (further malformed lines are only counted)", malformed_line.line_number, malformed_line.reason),
+            _ => return,
+        };
+
+        let line = self.printer.format_finding(Severity::Warning, "Malformed input", &line);
+        self.print_lines(&[line]);
+    }
+
+    pub fn finish(&mut self, cli: &Cli, saturation_state: &SaturationState, report_policy: &ReportPolicy, malformed_lines: usize) {
+        if let Some(status_bar) = self.status_bar.as_mut() {
+            status_bar.finish(&self.printer);
+        }
+
+        if let Some(metrics_export) = self.metrics_export.as_mut() {
+            if let Err(error) = metrics_export.flush() {
+                self.printer.print_error("Metrics", &format!("could not write metrics: {error}"));
+            }
+        }
+
+        if let Some(witness_writer) = self.witness_writer.as_mut() {
+            if let Err(error) = witness_writer.flush() {
+                self.printer.print_error("Witness", &format!("could not write witnesses: {error}"));
+            }
+        }
+
+        if let Some(path) = &cli.html_report {
+            if let Err(error) = HtmlReport::new(saturation_state, &report_policy.findings).write(path) {
+                self.printer.print_error("HTML report", &format!("could not write {}: {error}", path.display()));
+            }
+        }
+
+        self.print_final_report(saturation_state, report_policy);
+        if malformed_lines > 0 {
+            self.printer.print_warning("Malformed input", &format!("{malformed_lines} lines skipped"));
+        }
+    }
+
+    fn print_final_report(&self, saturation_state: &SaturationState, report_policy: &ReportPolicy) {
+        let saturations = &saturation_state.saturations;
+        for (position, saturation) in saturations.iter().enumerate() {
+            let end = saturations.get(position + 1).map(|next| next.first_iteration_index).unwrap_or(saturation_state.iterations.len());
+            let findings: Vec<&Finding> = report_policy.findings.iter().filter(|finding| (saturation.first_iteration_index..end).contains(&finding.iteration_index)).collect();
+            self.print_saturation_report(saturation, &saturation_state.iterations[saturation.first_iteration_index..end], &findings);
+        }
+    }
+
+    fn print_saturation_report(&self, saturation: &Saturation, iterations: &[Iteration], findings: &[&Finding]) {
+        let mut findings_per_header: BTreeMap<(&String, Severity), usize> = BTreeMap::new();
+        for finding in findings.iter() {
+            *findings_per_header.entry((&finding.header, finding.severity)).or_default() += 1;
+        }
+
+        self.printer.print_group_separator();
+        if let Some(description) = &saturation.description {
+            self.printer.print(description);
+        }
+        if !saturation.initial_clauses.is_empty() {
+            let count = |kind: ClauseKind| saturation.initial_clauses.iter().filter(|initial_clause| initial_clause.kind == kind).count();
+            let (attacker, process, equational) = (count(ClauseKind::Attacker), count(ClauseKind::Process), count(ClauseKind::Equational));
+            self.printer.print(&format!("Initial clauses: {} ({attacker} attacker, {process} process, {equational} equational)", saturation.initial_clauses.len()));
+        }
+        let progress = iterations.last().map(|iteration| iteration.progress.to_string()).unwrap_or("none".to_string());
+        self.printer.print(&format!("Iterations: {}, last: {progress}", iterations.len()));
+        if let Some(maximum) = iterations.iter().filter_map(|iteration| iteration.derivation_depth).max() {
+            self.printer.print(&format!("Derivation depth: at most {maximum}"));
+        }
+        if !saturation.clauses.clauses.is_empty() {
+            for line in describe_queue(&saturation.clauses) {
+                self.printer.print(&line);
+            }
+        }
+        match saturation.phase {
+            SaturationPhase::Unknown => {}
+            SaturationPhase::Completing => self.printer.print(&"Saturation: not completed".to_string()),
+            SaturationPhase::Completed => self.printer.print(&"Saturation: completed".to_string()),
+        }
+        self.printer.print(&format!("Findings: {}", findings.len()));
+        for ((header, severity), count) in findings_per_header {
+            let line = format!("{count} times");
+            match severity {
+                Severity::Info => self.printer.print_info(header, &line),
+                Severity::Warning => self.printer.print_warning(header, &line),
+                Severity::Error => self.printer.print_error(header, &line),
+            }
+        }
+        for result in saturation.results.iter() {
+            self.printer.print(result);
+        }
+    }
+}
+
+// number of predicates listed as starved in the final report
+const MAX_STARVED_PREDICATES: usize = 3;
+
+fn describe_queue(clauses: &ClauseTracker) -> Vec<String> {
+    let mut waiting_times: Vec<usize> = clauses.clauses.values().filter_map(|clause| clause.waiting_time()).collect();
+    waiting_times.sort();
+    let dropped = clauses.never_selected().filter(|clause| clause.dropped_iteration.is_some()).count();
+    let average = waiting_times.iter().sum::<usize>() as f64 / waiting_times.len().max(1) as f64;
+    let maximum = waiting_times.last().copied().unwrap_or(0);
+
+    let mut lines = vec![format!(
+        "Queue: {} clauses, {} selected after {average:.1} iterations on average (at most {maximum}), {dropped} dropped without selection, {} still queued",
+        clauses.clauses.len(),
+        waiting_times.len(),
+        clauses.queued().count()
+    )];
+
+    if !waiting_times.is_empty() {
+        let percentile = |percent: usize| waiting_times[(waiting_times.len() - 1) * percent / 100];
+        lines.push(format!("Queue waiting times: median {}, 90th percentile {}", percentile(50), percentile(90)));
+        lines.push(format!("Queue order: {} of {} selections were not the head of the queue", clauses.out_of_order_selections, waiting_times.len()));
+    }
+
+    let requeued: Vec<usize> = clauses.clauses.values().map(|clause| clause.queued_count).filter(|count| *count > 1).collect();
+    if let Some(maximum) = requeued.iter().max() {
+        lines.push(format!("Requeued clauses: {} queued again after an equal clause, at most {maximum} times", requeued.len()));
+    }
+
+    let statistics = clauses.predicate_statistics();
+    let mut starved: Vec<_> = statistics.iter().filter(|(_, statistics)| statistics.still_queued > 0).collect();
+    starved.sort_by(|(_, a), (_, b)| b.average_age.total_cmp(&a.average_age));
+    let starved: Vec<String> = starved
+        .iter()
+        .take(MAX_STARVED_PREDICATES)
+        .map(|(predicate, statistics)| format!("{predicate} ({} queued for {:.1} iterations on average, {} selected)", statistics.still_queued, statistics.average_age, statistics.selected))
+        .collect();
+    if !starved.is_empty() {
+        lines.push(format!("Starved predicates: {}", starved.join(", ")));
+    }
+
+    lines
+}
+
+#[test]
+fn test_output_filter() {
+    use clap::Parser;
+
+    let iteration = Iteration::for_test(1, "mess2(c[],x_1) -> att2(x_1,x_1)", Some("mess2(c[],x_1)"));
+    let summary = || IterationSummary::for_test();
+
+    let cli = Cli::parse_from(["proverif_terminator", "--every", "3"]);
+    let mut filter = OutputFilter::new(&cli);
+    let visible: Vec<bool> = (0..6).map(|_| filter.apply(&cli, &iteration, &mut summary())).collect();
+    assert_eq!(visible, vec![true, false, false, true, false, false]);
+
+    let cli = Cli::parse_from(["proverif_terminator", "--quiet", "--min-severity", "warning"]);
+    let mut filter = OutputFilter::new(&cli);
+    let mut with_info = summary();
+    with_info.add_info("Cycle".to_string(), "size 2".to_string());
+    assert!(!filter.apply(&cli, &iteration, &mut with_info));
+    let mut with_warning = summary();
+    with_warning.add_warning("Cycle".to_string(), "size 2".to_string());
+    assert!(filter.apply(&cli, &iteration, &mut with_warning));
+
+    let cli = Cli::parse_from(["proverif_terminator", "--only-predicate", "att2"]);
+    let mut filter = OutputFilter::new(&cli);
+    assert!(!filter.apply(&cli, &iteration, &mut summary()));
+}
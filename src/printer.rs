@@ -1,7 +1,50 @@
-pub struct Printer {}
+use crate::iteration_summary::Severity;
+use clap::ValueEnum;
+use std::io::{IsTerminal, Write};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, ValueEnum)]
+pub enum ColorChoice {
+    /// colored if printing to a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+/// how findings are rendered
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Theme {
+    Plain,
+    Colored,
+    /// list entries with emphasis, for pasting into tickets
+    Markdown,
+}
+
+impl Theme {
+    pub fn detect(color: ColorChoice, markdown: bool) -> Self {
+        if markdown {
+            return Theme::Markdown;
+        }
+
+        let colored = match color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && std::io::stdout().is_terminal(),
+        };
+
+        if colored {
+            Theme::Colored
+        } else {
+            Theme::Plain
+        }
+    }
+}
+
+pub struct Printer {
+    theme: Theme,
+}
 impl Printer {
-    pub fn new() -> Self {
-        Printer {}
+    pub fn new(theme: Theme) -> Self {
+        Printer { theme }
     }
 
     pub fn print(&self, line: &String) {
@@ -10,13 +53,61 @@ impl Printer {
     pub fn print_group_separator(&self) {
         println!();
     }
-    pub fn print_info(&self, header: &String, line: &String) {
-        self.print(&format!("\x1b[93m{}\x1b[0m: {}", header, line));
+    pub fn print_info(&self, header: &str, line: &str) {
+        self.print(&self.format_finding(Severity::Info, header, line));
     }
-    pub fn print_warning(&self, header: &String, line: &String) {
-        self.print(&format!("\x1b[38;5;208m{}\x1b[0m: {}", header, line));
+    pub fn print_warning(&self, header: &str, line: &str) {
+        self.print(&self.format_finding(Severity::Warning, header, line));
     }
-    pub fn print_error(&self, header: &String, line: &String) {
-        self.print(&format!("\x1b[91m{}\x1b[0m: {}", header, line));
+    pub fn print_error(&self, header: &str, line: &str) {
+        self.print(&self.format_finding(Severity::Error, header, line));
     }
+
+    pub fn format_finding(&self, severity: Severity, header: &str, line: &str) -> String {
+        match self.theme {
+            Theme::Plain => format!("[{}] {header}: {line}", severity_name(severity)),
+            Theme::Colored => {
+                let color = match severity {
+                    Severity::Info => "93",
+                    Severity::Warning => "38;5;208",
+                    Severity::Error => "91",
+                };
+                format!("\x1b[{color}m{header}\x1b[0m: {line}")
+            }
+            Theme::Markdown => format!("- **{header}** _{}_: {line}", severity_name(severity)),
+        }
+    }
+
+    /// prints the lines without a final newline, so they can be cleared again
+    pub fn print_footer(&self, lines: &[String]) {
+        print!("{}", lines.join("\n"));
+        let _ = std::io::stdout().flush();
+    }
+    pub fn clear_footer(&self, line_count: usize) {
+        if line_count == 0 {
+            return;
+        }
+
+        print!("\r\x1b[2K{}", "\x1b[1A\x1b[2K".repeat(line_count - 1));
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+#[test]
+fn test_format_finding() {
+    assert_eq!(Theme::detect(ColorChoice::Always, false), Theme::Colored);
+    assert_eq!(Theme::detect(ColorChoice::Never, false), Theme::Plain);
+    assert_eq!(Theme::detect(ColorChoice::Always, true), Theme::Markdown);
+
+    let finding = |theme: Theme| Printer::new(theme).format_finding(Severity::Warning, "Hypothesis cycle", "size 2, repeat 60");
+    assert_eq!(finding(Theme::Plain), "[warning] Hypothesis cycle: size 2, repeat 60");
+    assert_eq!(finding(Theme::Colored), "\x1b[38;5;208mHypothesis cycle\x1b[0m: size 2, repeat 60");
+    assert_eq!(finding(Theme::Markdown), "- **Hypothesis cycle** _warning_: size 2, repeat 60");
 }
@@ -0,0 +1,85 @@
+use crate::clause_tracker::ClauseTracker;
+use crate::iteration_summary::IterationSummary;
+use crate::report_policy::Thresholds;
+use crate::saturation_state::Iteration;
+use crate::Cli;
+use std::collections::HashMap;
+
+/// flags clauses which are queued again and again, predicates whose backlog keeps growing, and selections out of FIFO order
+pub struct QueueChecker {
+    requeue_thresholds: Thresholds,
+    backlog_thresholds: Thresholds,
+    // backlog per predicate at which it was reported last, the backlog is reported again once it doubled
+    reported_backlogs: HashMap<String, usize>,
+    reported_out_of_order: bool,
+}
+
+impl QueueChecker {
+    pub fn new(cli: &Cli) -> Self {
+        QueueChecker {
+            requeue_thresholds: cli.requeue_thresholds,
+            backlog_thresholds: cli.backlog_thresholds,
+            reported_backlogs: HashMap::new(),
+            reported_out_of_order: false,
+        }
+    }
+
+    pub fn check(&mut self, iteration: &Iteration, clauses: &ClauseTracker, printer: &mut IterationSummary) {
+        for entry in iteration.new_queue_entries.iter() {
+            let Some(clause) = clauses.clauses.get(&entry.number) else {
+                continue;
+            };
+
+            // only report when the count reaches the next severity
+            let severity = self.requeue_thresholds.severity(clause.queued_count);
+            if let Some(severity) = severity.filter(|severity| Some(*severity) != self.requeue_thresholds.severity(clause.queued_count - 1)) {
+                printer.add_finding(severity, "Requeued clause".to_string(), format!("queued {} times, now as rule {}: {}", clause.queued_count, entry.number, entry.rule));
+            }
+        }
+
+        let queue_size: usize = clauses.backlog.values().sum();
+        for (predicate, backlog) in clauses.backlog.iter() {
+            let Some(severity) = self.backlog_thresholds.severity(*backlog) else {
+                continue;
+            };
+
+            let reported_backlog = self.reported_backlogs.entry(predicate.clone()).or_default();
+            if *backlog >= 2 * *reported_backlog {
+                *reported_backlog = *backlog;
+                printer.add_finding(severity, "Backlog".to_string(), format!("{backlog} queued clauses conclude {predicate} ({}% of the queue)", 100 * backlog / queue_size.max(1)));
+            }
+        }
+
+        if let (Some(skipped_head), Some(selected)) = (clauses.skipped_head, iteration.selected_rule_number) {
+            if !self.reported_out_of_order {
+                self.reported_out_of_order = true;
+                printer.add_info("Queue order".to_string(), format!("rule {selected} selected before rule {skipped_head}, which was queued earlier"));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_queue_checker() {
+    use crate::saturation_state::QueueEntry;
+    use clap::Parser;
+
+    let cli = Cli::parse_from(["proverif_terminator", "--requeue-thresholds", "1,2,10", "--backlog-thresholds", "1,10,100"]);
+    let mut checker = QueueChecker::new(&cli);
+    let mut tracker = ClauseTracker::new();
+    let mut findings = vec![];
+
+    let queues = [vec![(1, "mess2(c[],a[])"), (2, "att2(a[],a[])")], vec![(2, "att2(a[],a[])"), (3, "mess2(c[],a[])"), (4, "mess2(c[],b[])"), (5, "mess2(c[],d[])")]];
+    for (iteration_number, queue) in queues.iter().enumerate() {
+        let mut iteration = Iteration::for_test(iteration_number, "mess2(c[],a[])", None);
+        iteration.selected_rule_number = tracker.select(&iteration.query, iteration_number);
+        let dump: Vec<QueueEntry> = queue.iter().map(|(number, rule)| QueueEntry::new(*number, rule.to_string())).collect();
+        iteration.new_queue_entries = tracker.update_queue(&dump, iteration_number, |_| None);
+
+        let mut summary = IterationSummary::for_test();
+        checker.check(&iteration, &tracker, &mut summary);
+        findings.extend(summary.findings().iter().map(|(severity, header, line)| format!("{severity:?} {header}: {line}")));
+    }
+
+    assert_eq!(findings, vec!["Info Requeued clause: queued 2 times, now as rule 3: mess2(c[],a[])", "Info Backlog: 3 queued clauses conclude mess2 (75% of the queue)"]);
+}
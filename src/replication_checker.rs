@@ -0,0 +1,90 @@
+use crate::iteration_summary::IterationSummary;
+use crate::saturation_state::Iteration;
+use crate::term::SessionAnnotations;
+use crate::witness::LoopSpan;
+
+// number of new maxima (after the first fact with annotations) before warning
+const REPLICATION_GROWTH_STREAK: usize = 3;
+
+#[derive(Default)]
+struct Growth {
+    maximum: usize,
+    increases: usize,
+}
+
+impl Growth {
+    /// returns whether the value is a new maximum after the value increased often enough
+    fn update(&mut self, value: usize) -> bool {
+        if value <= self.maximum {
+            return false;
+        }
+        if self.maximum > 0 {
+            self.increases += 1;
+        }
+        self.maximum = value;
+
+        self.increases >= REPLICATION_GROWTH_STREAK
+    }
+}
+
+/// warns when the selected facts involve more and more sessions of replicated processes, e.g. a process instantiating itself again and again
+pub struct ReplicationChecker {
+    indices: Growth,
+    replication_depth: Growth,
+}
+
+impl ReplicationChecker {
+    pub fn new() -> Self {
+        ReplicationChecker {
+            indices: Growth::default(),
+            replication_depth: Growth::default(),
+        }
+    }
+
+    pub fn check(&mut self, iteration: &Iteration, printer: &mut IterationSummary) {
+        let Some(fact) = iteration.selected_fact().and_then(|f| f.term.as_ref()) else {
+            return;
+        };
+
+        let annotations = fact.session_annotations();
+        let predicate = fact.predicate().unwrap_or("?");
+        if self.indices.update(annotations.indices.len()) {
+            let description = format!("{} distinct session indices in one {predicate} fact, increased {} times{}", annotations.indices.len(), self.indices.increases, describe_names(&annotations));
+            printer.add_warning("Replication".to_string(), description.clone());
+            printer.add_loop("Replication".to_string(), description, LoopSpan::Ancestry);
+        }
+        if self.replication_depth.update(annotations.replication_depth) {
+            let description = format!("{} nested replication markers in one {predicate} fact, increased {} times{}", annotations.replication_depth, self.replication_depth.increases, describe_names(&annotations));
+            printer.add_warning("Replication".to_string(), description.clone());
+            printer.add_loop("Replication".to_string(), description, LoopSpan::Ancestry);
+        }
+    }
+}
+
+fn describe_names(annotations: &SessionAnnotations) -> String {
+    match annotations.innermost_names.is_empty() {
+        true => String::new(),
+        false => format!(", names of the innermost replicated process: {}", annotations.innermost_names.iter().cloned().collect::<Vec<_>>().join(", ")),
+    }
+}
+
+#[test]
+fn test_growing_replication() {
+    let mut checker = ReplicationChecker::new();
+    let mut warnings = vec![];
+    let facts = [
+        "att2(n[!1 = @sid],a)",
+        "att2(n[!1 = @sid],n[!1 = @sid_1])",
+        "att2(n[!1 = @sid],n[!1 = @sid_1])",
+        "att2(n[!1 = @sid],(n[!1 = @sid_1],n[!1 = @sid_2]))",
+        "att2(n[!1 = @sid,!2 = @sid_3],(n[!1 = @sid_1],n[!1 = @sid_2]))",
+    ];
+    for fact in facts {
+        let iteration = Iteration::for_test(1, "", Some(fact));
+        let mut summary = IterationSummary::for_test();
+        checker.check(&iteration, &mut summary);
+        warnings.extend(summary.findings().iter().map(|(_, _, line)| line.to_string()));
+    }
+
+    assert_eq!(warnings, vec!["4 distinct session indices in one att2 fact, increased 3 times, names of the innermost replicated process: n"]);
+}
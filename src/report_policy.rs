@@ -0,0 +1,167 @@
+use crate::iteration_summary::{IterationSummary, Severity};
+use crate::Cli;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// values above which a finding is reported as info, warning or error
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Thresholds {
+    pub info: usize,
+    pub warning: usize,
+    pub error: usize,
+}
+
+impl Thresholds {
+    pub fn severity(&self, value: usize) -> Option<Severity> {
+        if value > self.error {
+            return Some(Severity::Error);
+        }
+
+        if value > self.warning {
+            return Some(Severity::Warning);
+        }
+
+        if value > self.info {
+            return Some(Severity::Info);
+        }
+
+        None
+    }
+}
+
+impl FromStr for Thresholds {
+    type Err = String;
+
+    /// parses `<info>,<warning>,<error>`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let values = input.split(',').map(|value| value.trim().parse::<usize>().map_err(|_| format!("invalid threshold {value}"))).collect::<Result<Vec<usize>, String>>()?;
+
+        match values.as_slice() {
+            [info, warning, error] => Ok(Thresholds { info: *info, warning: *warning, error: *error }),
+            _ => Err("expected three thresholds: <info>,<warning>,<error>".to_string()),
+        }
+    }
+}
+
+/// parses `<key>=<info>,<warning>,<error>`
+pub fn parse_keyed_thresholds(input: &str) -> Result<(String, Thresholds), String> {
+    let (key, thresholds) = input.split_once('=').ok_or("expected <key>=<info>,<warning>,<error>")?;
+
+    Ok((key.trim().to_string(), thresholds.parse()?))
+}
+
+/// thresholds of a detector, which can be overwritten per predicate
+pub struct ThresholdPolicy {
+    default: Thresholds,
+    per_predicate: HashMap<String, Thresholds>,
+}
+
+impl ThresholdPolicy {
+    pub fn new(default: Thresholds, detector: &str, per_detector: &[(String, Thresholds)], per_predicate: &[(String, Thresholds)]) -> Self {
+        let default = per_detector.iter().rev().find(|(key, _)| key.eq_ignore_ascii_case(detector)).map(|(_, thresholds)| *thresholds).unwrap_or(default);
+        let per_predicate = per_predicate.iter().cloned().collect();
+
+        ThresholdPolicy { default, per_predicate }
+    }
+
+    /// the strictest thresholds of the involved predicates, or the default if none are configured
+    pub fn thresholds<'a>(&self, predicates: impl Iterator<Item = &'a str>) -> Thresholds {
+        predicates.filter_map(|predicate| self.per_predicate.get(predicate)).min_by_key(|thresholds| thresholds.info).copied().unwrap_or(self.default)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    // index into the iterations of all saturations
+    pub iteration_index: usize,
+    pub iteration: usize,
+    pub severity: Severity,
+    pub header: String,
+    pub line: String,
+}
+
+/// decides which findings are shown, collects them, and remembers the most severe one for the exit code
+pub struct ReportPolicy {
+    dedupe: bool,
+    interval: Option<usize>,
+    fail_on: Option<Severity>,
+
+    reported: HashSet<(String, String)>,
+    last_reported: HashMap<String, (usize, Severity)>,
+    max_severity: Option<Severity>,
+
+    pub findings: Vec<Finding>,
+}
+
+impl ReportPolicy {
+    pub fn new(cli: &Cli) -> Self {
+        ReportPolicy {
+            dedupe: cli.dedupe_findings,
+            interval: cli.finding_interval,
+            fail_on: cli.fail_on,
+
+            reported: HashSet::new(),
+            last_reported: HashMap::new(),
+            max_severity: None,
+
+            findings: Vec::new(),
+        }
+    }
+
+    pub fn apply(&mut self, iteration_index: usize, iteration: usize, summary: &mut IterationSummary) {
+        for (severity, _, _) in summary.findings() {
+            self.max_severity = self.max_severity.max(Some(severity));
+        }
+
+        summary.retain_findings(|severity, header, line| {
+            if self.dedupe && !self.reported.insert((header.clone(), line.clone())) {
+                return false;
+            }
+
+            // rate limit per header, but always let escalations through
+            if let Some(interval) = self.interval {
+                if let Some((last_iteration, last_severity)) = self.last_reported.get(header) {
+                    if iteration < last_iteration + interval && severity <= *last_severity {
+                        return false;
+                    }
+                }
+            }
+
+            self.last_reported.insert(header.clone(), (iteration, severity));
+            true
+        });
+
+        for (severity, header, line) in summary.findings() {
+            let (header, line) = (header.clone(), line.clone());
+            self.findings.push(Finding { iteration_index, iteration, severity, header, line });
+        }
+    }
+
+    /// iteration numbers start again with every saturation, hence so do deduplication and rate limiting
+    pub fn start_saturation(&mut self) {
+        self.reported.clear();
+        self.last_reported.clear();
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match (self.fail_on, self.max_severity) {
+            (Some(fail_on), Some(max_severity)) if max_severity >= fail_on => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[test]
+fn test_thresholds() {
+    let thresholds: Thresholds = "10,100,1000".parse().unwrap();
+    assert_eq!(thresholds.severity(10), None);
+    assert_eq!(thresholds.severity(11), Some(Severity::Info));
+    assert_eq!(thresholds.severity(1001), Some(Severity::Error));
+    assert!("10,100".parse::<Thresholds>().is_err());
+
+    let per_detector = vec![parse_keyed_thresholds("query=1,2,3").unwrap()];
+    let per_predicate = vec![parse_keyed_thresholds("mess2=5,6,7").unwrap(), parse_keyed_thresholds("att2=4,8,9").unwrap()];
+    let policy = ThresholdPolicy::new(thresholds, "Query", &per_detector, &per_predicate);
+    assert_eq!(policy.thresholds(["table2"].into_iter()).info, 1);
+    assert_eq!(policy.thresholds(["mess2", "att2"].into_iter()).info, 4);
+}
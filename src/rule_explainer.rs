@@ -1,33 +1,101 @@
-use crate::saturation_state::Iteration;
+use crate::clause::canonicalize;
+use crate::saturation_state::{InitialClause, Iteration};
+use std::fmt::{Display, Formatter};
 
 pub struct QueryExplainer {}
 
-impl QueryExplainer {
-    pub fn get_ancestry(iterations: &[Iteration]) -> Vec<Iteration> {
-        let last_iteration = iterations.last().unwrap();
-        let mut ancestry: Vec<Iteration> = vec![last_iteration.clone()];
+/// how an ancestor was found, from the most to the least reliable
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Link {
+    /// the iteration which is explained
+    Selected,
+    /// a new queue entry of the ancestor is the selected clause as printed
+    Exact,
+    /// equal up to renaming of the variables and order of the hypotheses
+    Canonical,
+    /// the selected clause was at the head of the queue with a number the ancestor added
+    RuleNumber,
+}
+
+#[derive(Clone)]
+pub struct Ancestor {
+    pub iteration: Iteration,
+    pub link: Link,
+}
 
-        let mut candidate = &last_iteration.query;
+impl Link {
+    pub fn confidence(&self) -> f64 {
+        match self {
+            Link::Selected | Link::Exact => 1.0,
+            Link::Canonical => 0.9,
+            Link::RuleNumber => 0.6,
+        }
+    }
+}
 
-        for i in (0..iterations.len()).rev() {
-            let iteration = &iterations[i];
+impl Display for Link {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Link::Selected => write!(f, "selected"),
+            Link::Exact => write!(f, "exact match"),
+            Link::Canonical => write!(f, "match up to renaming"),
+            Link::RuleNumber => write!(f, "match by rule number"),
+        }
+    }
+}
 
-            for new_queue_entry in &iteration.new_queue_entries {
-                if *new_queue_entry == *candidate {
-                    ancestry.push(iteration.clone());
+impl QueryExplainer {
+    /// the last iteration, followed by the iterations which added the clause selected by the previous entry to the queue,
+    /// up to an iteration selecting one of the initial clauses
+    pub fn get_ancestry(iterations: &[Iteration], initial_clauses: &[InitialClause]) -> Vec<Ancestor> {
+        let Some((last_iteration, earlier_iterations)) = iterations.split_last() else {
+            return vec![];
+        };
+        let mut ancestry = vec![Ancestor {
+            iteration: last_iteration.clone(),
+            link: Link::Selected,
+        }];
 
-                    candidate = &iteration.query;
-                    break;
-                }
+        let mut child = last_iteration;
+        let mut candidate = canonicalize(&child.query);
+        for iteration in earlier_iterations.iter().rev() {
+            if initial_clauses.iter().any(|initial_clause| initial_clause.canonical == candidate) {
+                break;
+            }
+            if let Some(link) = Self::find_link(iteration, child, &candidate) {
+                ancestry.push(Ancestor { iteration: iteration.clone(), link });
+                child = iteration;
+                candidate = canonicalize(&child.query);
             }
         }
 
         ancestry
     }
+
+    fn find_link(iteration: &Iteration, child: &Iteration, canonical_query: &str) -> Option<Link> {
+        let entries = &iteration.new_queue_entries;
+        if entries.iter().any(|entry| entry.rule == child.query) {
+            return Some(Link::Exact);
+        }
+        if entries.iter().any(|entry| entry.canonical == canonical_query) {
+            return Some(Link::Canonical);
+        }
+
+        let selected_rule_number = child.selected_rule_number?;
+        entries.iter().any(|entry| entry.number == selected_rule_number).then_some(Link::RuleNumber)
+    }
+
+    /// the initial clause the oldest ancestor selected, i.e. where the derivation starts
+    pub fn get_root<'a>(ancestry: &[Ancestor], initial_clauses: &'a [InitialClause]) -> Option<&'a InitialClause> {
+        let oldest_ancestor = canonicalize(&ancestry.last()?.iteration.query);
+        initial_clauses.iter().find(|initial_clause| initial_clause.canonical == oldest_ancestor)
+    }
 }
 
 #[test]
 fn test_get_new_queue_entries() {
+    use crate::saturation_state::QueueEntry;
+
     let progress = crate::saturation_state::SaturationProgress {
         iteration: 0,
         in_queue: 2,
@@ -35,7 +103,9 @@ fn test_get_new_queue_entries() {
         with_conclusion_selected: 0,
     };
     let iteration0 = Iteration {
-        new_queue_entries: vec!["a".to_string(), "b".to_string()],
+        new_queue_entries: vec![QueueEntry::new(0, "a".to_string()), QueueEntry::new(1, "b".to_string())],
+        selected_rule_number: None,
+        derivation_depth: None,
         query: "c".to_string(),
         progress,
         conclusion_fact_selected: None,
@@ -49,7 +119,9 @@ fn test_get_new_queue_entries() {
         with_conclusion_selected: 0,
     };
     let iteration1 = Iteration {
-        new_queue_entries: vec!["d".to_string()],
+        new_queue_entries: vec![QueueEntry::new(2, "d".to_string())],
+        selected_rule_number: None,
+        derivation_depth: None,
         query: "a".to_string(),
         progress,
         conclusion_fact_selected: None,
@@ -64,6 +136,8 @@ fn test_get_new_queue_entries() {
     };
     let iteration2 = Iteration {
         new_queue_entries: vec![],
+        selected_rule_number: None,
+        derivation_depth: None,
         query: "b".to_string(),
         progress,
         conclusion_fact_selected: None,
@@ -77,7 +151,9 @@ fn test_get_new_queue_entries() {
         with_conclusion_selected: 0,
     };
     let iteration3 = Iteration {
-        new_queue_entries: vec!["e".to_string(), "f".to_string()],
+        new_queue_entries: vec![QueueEntry::new(3, "e".to_string()), QueueEntry::new(4, "f".to_string())],
+        selected_rule_number: None,
+        derivation_depth: None,
         query: "d".to_string(),
         progress,
         conclusion_fact_selected: None,
@@ -86,5 +162,46 @@ fn test_get_new_queue_entries() {
 
     let iterations: Vec<Iteration> = vec![iteration0, iteration1, iteration2, iteration3];
     let ancestry = vec![3, 1, 0];
-    assert_eq!(QueryExplainer::get_ancestry(&iterations).iter().map(|x| x.progress.iteration).collect::<Vec<usize>>(), ancestry);
+    assert_eq!(QueryExplainer::get_ancestry(&iterations, &[]).iter().map(|x| x.iteration.progress.iteration).collect::<Vec<usize>>(), ancestry);
+}
+
+#[test]
+fn test_ancestry_links() {
+    use crate::saturation_state::QueueEntry;
+
+    let mut iterations = vec![
+        Iteration::for_test(1, "mess2(c[],x_1) -> att2(x_1,x_1)", None),
+        Iteration::for_test(2, "att2(v_3,y_4) && mess2(c[],v_3) -> att2(f(v_3),y_4)", None),
+        Iteration::for_test(3, "att2(a[],x_2)", None),
+    ];
+    iterations[0].new_queue_entries = vec![QueueEntry::new(7, "mess2(c[],y_1) && att2(y_1,x_1) -> att2(f(y_1),x_1)".to_string())];
+    iterations[1].new_queue_entries = vec![QueueEntry::new(8, "att2(a[],a[])".to_string())];
+    iterations[2].selected_rule_number = Some(8);
+
+    let ancestry = QueryExplainer::get_ancestry(&iterations, &[]);
+    let links: Vec<(usize, Link)> = ancestry.iter().map(|ancestor| (ancestor.iteration.progress.iteration, ancestor.link)).collect();
+    assert_eq!(links, vec![(3, Link::Selected), (2, Link::RuleNumber), (1, Link::Canonical)]);
+}
+
+#[test]
+fn test_get_root() {
+    use crate::saturation_state::SaturationState;
+
+    let mut saturation_state = SaturationState::new();
+    saturation_state.add_initial_clause(None, "att2(x_1,y_1) && att2(x_2,y_2) -> att2((x_1,x_2),(y_1,y_2))");
+    saturation_state.add_initial_clause(None, "mess2(c[],x_1) -> att2(x_1,x_1)");
+    let initial_clauses = &saturation_state.current_saturation().initial_clauses;
+
+    let ancestor = |iteration: Iteration| Ancestor { iteration, link: Link::Exact };
+    let ancestry = vec![ancestor(Iteration::for_test(3, "att2(a[],a[])", None)), ancestor(Iteration::for_test(1, "mess2(c[],v_7) -> att2(v_7,v_7)", None))];
+    let root = QueryExplainer::get_root(&ancestry, initial_clauses).unwrap();
+    assert_eq!(root.to_string(), "initial clause 1 (process): mess2(c[],x_1) -> att2(x_1,x_1)");
+    assert!(QueryExplainer::get_root(&ancestry[..1], initial_clauses).is_none());
+
+    // the ancestry stops at the initial clause, even if an earlier iteration added an equal clause to the queue
+    let mut iterations = vec![Iteration::for_test(1, "att2(x_1,y_1) && att2(x_2,y_2) -> att2((x_1,x_2),(y_1,y_2))", None), Iteration::for_test(2, "mess2(c[],v_7) -> att2(v_7,v_7)", None)];
+    iterations[0].new_queue_entries = vec![crate::saturation_state::QueueEntry::new(5, "mess2(c[],x_1) -> att2(x_1,x_1)".to_string())];
+    let ancestry = QueryExplainer::get_ancestry(&iterations, initial_clauses);
+    assert_eq!(ancestry.iter().map(|ancestor| ancestor.iteration.progress.iteration).collect::<Vec<usize>>(), vec![2]);
+    assert_eq!(QueryExplainer::get_ancestry(&iterations, &[]).len(), 2);
 }
@@ -1,4 +1,7 @@
+use crate::clause::{canonicalize, Clause, ClauseKind};
+use crate::clause_tracker::ClauseTracker;
 use crate::iteration_summary::IterationSummary;
+use crate::term::{Side, Term, TermMetrics};
 use std::cmp::PartialEq;
 use std::fmt::{Display, Formatter};
 use std::mem;
@@ -9,11 +12,47 @@ pub struct SaturationState {
     hypothesis_fact_selected: Option<SelectedFact>,
     conclusion_fact_selected: Option<SelectedFact>,
 
-    queue_entries: Vec<String>,
-    last_iteration_queue_entries: Vec<String>,
+    queue_entries: Vec<QueueEntry>,
 
     pub iterations: Vec<Iteration>,
-    pub hypothesis_selected_fact_history: Vec<(String, u32)>,
+    pub saturations: Vec<Saturation>,
+}
+
+/// ProVerif saturates the clauses again for every process (and group of queries), the iterations of all saturations are kept
+/// in `SaturationState::iterations` and segmented by `first_iteration_index`
+pub struct Saturation {
+    /// e.g. "Query not attacker(s[]) in process 0", unknown if the log does not start with a query header
+    pub description: Option<String>,
+    pub first_iteration_index: usize,
+    pub phase: SaturationPhase,
+    pub results: Vec<String>,
+    /// printed after "Starting rules" with `set verboseRules = true`
+    pub initial_clauses: Vec<InitialClause>,
+    pub clauses: ClauseTracker,
+}
+
+/// root of the derivations
+#[derive(Clone)]
+pub struct InitialClause {
+    pub number: usize,
+    pub clause: String,
+    pub canonical: String,
+    pub kind: ClauseKind,
+}
+
+impl Display for InitialClause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "initial clause {} ({}): {}", self.number, self.kind, self.clause)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SaturationPhase {
+    /// no "Completing..." line seen yet
+    Unknown,
+    Completing,
+    /// "Starting query" seen, hence the saturation terminated
+    Completed,
 }
 
 #[derive(Clone)]
@@ -23,7 +62,25 @@ pub struct Iteration {
     pub hypothesis_fact_selected: Option<SelectedFact>,
     pub conclusion_fact_selected: Option<SelectedFact>,
 
-    pub new_queue_entries: Vec<String>,
+    pub new_queue_entries: Vec<QueueEntry>,
+    /// ProVerif rule number of the queued clause selected in this iteration
+    pub selected_rule_number: Option<usize>,
+    /// number of resolution steps between an initial clause and the selected clause, following the queue entries
+    pub derivation_depth: Option<usize>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct QueueEntry {
+    pub number: usize,
+    pub rule: String,
+    pub canonical: String,
+}
+
+impl QueueEntry {
+    pub fn new(number: usize, rule: String) -> Self {
+        let canonical = canonicalize(&rule);
+        QueueEntry { number, rule, canonical }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -38,8 +95,72 @@ pub struct SaturationProgress {
 pub struct SelectedFact {
     pub fact: String,
 
-    #[allow(dead_code)] // not yet used
     pub fact_number: Option<usize>,
+
+    pub term: Option<Term>,
+}
+
+impl Iteration {
+    pub fn selected_fact(&self) -> Option<&SelectedFact> {
+        self.hypothesis_fact_selected.as_ref().or(self.conclusion_fact_selected.as_ref())
+    }
+
+    pub fn term_metrics(&self) -> Option<TermMetrics> {
+        let term = self.selected_fact()?.term.as_ref()?;
+        Some(TermMetrics::of_fact(term))
+    }
+
+    /// whether the selected fact or clause holds bi-facts or `choice`, else both sides see the same iteration
+    pub fn has_sides(&self) -> bool {
+        let selected_fact = self.selected_fact().and_then(|selected_fact| selected_fact.term.as_ref());
+        let query = Clause::parse(&self.query);
+        selected_fact.is_some_and(|term| term.has_sides()) || query.is_some_and(|clause| clause.hypotheses.iter().chain([&clause.conclusion]).any(|term| term.has_sides()))
+    }
+
+    /// the iteration as seen by one side of the biprocess, so detectors can run on each side separately
+    pub fn project(&self, side: Side) -> Iteration {
+        let project_clause = |clause: &String| Clause::parse(clause).map(|clause| clause.project(side).to_string()).unwrap_or(clause.clone());
+        let project_fact = |selected_fact: &SelectedFact| {
+            let term = selected_fact.term.as_ref().map(|term| term.project(side));
+            let fact = term.as_ref().map(|term| term.to_string()).unwrap_or(selected_fact.fact.clone());
+            SelectedFact { fact, fact_number: selected_fact.fact_number, term }
+        };
+
+        Iteration {
+            progress: self.progress,
+            query: project_clause(&self.query),
+            hypothesis_fact_selected: self.hypothesis_fact_selected.as_ref().map(project_fact),
+            conclusion_fact_selected: self.conclusion_fact_selected.as_ref().map(project_fact),
+            new_queue_entries: self.new_queue_entries.iter().map(|entry| QueueEntry::new(entry.number, project_clause(&entry.rule))).collect(),
+            selected_rule_number: self.selected_rule_number,
+            derivation_depth: self.derivation_depth,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn for_test(iteration: usize, query: &str, hypothesis_fact_selected: Option<&str>) -> Self {
+        let progress = SaturationProgress {
+            iteration,
+            with_conclusion_selected: 0,
+            with_hypothesis_selected: 0,
+            in_queue: 0,
+        };
+        let hypothesis_fact_selected = hypothesis_fact_selected.map(|fact| SelectedFact {
+            fact: fact.to_string(),
+            fact_number: Some(0),
+            term: Term::parse(fact),
+        });
+
+        Iteration {
+            progress,
+            query: query.to_string(),
+            hypothesis_fact_selected,
+            conclusion_fact_selected: None,
+            new_queue_entries: vec![],
+            selected_rule_number: None,
+            derivation_depth: None,
+        }
+    }
 }
 
 impl Display for SaturationProgress {
@@ -57,28 +178,99 @@ impl SaturationState {
             hypothesis_fact_selected: None,
 
             queue_entries: Vec::new(),
-            last_iteration_queue_entries: Vec::new(),
 
             iterations: Vec::new(),
-            hypothesis_selected_fact_history: Vec::new(),
+            saturations: vec![Saturation {
+                description: None,
+                first_iteration_index: 0,
+                phase: SaturationPhase::Unknown,
+                results: Vec::new(),
+                initial_clauses: Vec::new(),
+                clauses: ClauseTracker::new(),
+            }],
         }
     }
 
+    /// discards a partially read iteration, and starts a new segment (reusing the current one if nothing happened in it yet)
+    pub fn start_saturation(&mut self, description: String) {
+        self.progress = None;
+        self.query = None;
+        self.hypothesis_fact_selected = None;
+        self.conclusion_fact_selected = None;
+        self.queue_entries = Vec::new();
+
+        let saturation = Saturation {
+            description: Some(description),
+            first_iteration_index: self.iterations.len(),
+            phase: SaturationPhase::Unknown,
+            results: Vec::new(),
+            initial_clauses: Vec::new(),
+            clauses: ClauseTracker::new(),
+        };
+
+        let current = self.current_saturation();
+        if current.first_iteration_index == self.iterations.len() && current.results.is_empty() && current.initial_clauses.is_empty() {
+            *self.saturations.last_mut().unwrap() = saturation;
+        } else {
+            self.saturations.push(saturation);
+        }
+    }
+
+    pub fn current_saturation(&self) -> &Saturation {
+        self.saturations.last().unwrap()
+    }
+
+    pub fn set_saturation_phase(&mut self, phase: SaturationPhase) {
+        self.saturations.last_mut().unwrap().phase = phase;
+    }
+
+    pub fn add_result(&mut self, result: String) {
+        self.saturations.last_mut().unwrap().results.push(result);
+    }
+
+    /// numbered in order of appearance if the log does not number them; returns false if the clause cannot be parsed
+    pub fn add_initial_clause(&mut self, number: Option<usize>, clause: &str) -> bool {
+        let Some(parsed_clause) = Clause::parse(clause) else {
+            return false;
+        };
+
+        let initial_clauses = &mut self.saturations.last_mut().unwrap().initial_clauses;
+        initial_clauses.push(InitialClause {
+            number: number.unwrap_or(initial_clauses.len()),
+            clause: clause.to_string(),
+            canonical: canonicalize(clause),
+            kind: parsed_clause.kind(),
+        });
+        true
+    }
+
+    /// the iterations of the current saturation
+    pub fn current_iterations(&self) -> &[Iteration] {
+        &self.iterations[self.current_saturation().first_iteration_index..]
+    }
+
+    /// the saturation the iteration belongs to
+    pub fn saturation_of(&self, iteration_index: usize) -> &Saturation {
+        self.saturations.iter().rev().find(|saturation| saturation.first_iteration_index <= iteration_index).unwrap()
+    }
+
     pub fn set_query(&mut self, query: String) {
         self.query = Some(query);
     }
 
-    pub fn set_queue_entry(&mut self, _entry_number: usize, rule: String) {
+    pub fn set_queue_entry(&mut self, entry_number: usize, rule: String) {
         // assumes in order; which is a valid assumption
-        self.queue_entries.push(rule);
+        self.queue_entries.push(QueueEntry::new(entry_number, rule));
     }
 
     pub fn set_hypothesis_fact_selected(&mut self, fact: String, fact_number: usize) {
-        self.hypothesis_fact_selected = Some(SelectedFact { fact, fact_number: Some(fact_number) });
+        let term = Term::parse(&fact);
+        self.hypothesis_fact_selected = Some(SelectedFact { fact, fact_number: Some(fact_number), term });
     }
 
     pub fn set_conclusion_fact_selected(&mut self, fact: String) {
-        self.conclusion_fact_selected = Some(SelectedFact { fact, fact_number: None });
+        let term = Term::parse(&fact);
+        self.conclusion_fact_selected = Some(SelectedFact { fact, fact_number: None, term });
     }
 
     pub fn set_saturation_progress(&mut self, iteration: usize, with_conclusion_selected: usize, with_hypothesis_selected: usize, in_queue: usize) {
@@ -91,6 +283,12 @@ impl SaturationState {
     }
 
     pub fn complete_iteration(&mut self) -> Option<IterationSummary> {
+        // a dump before the first selection holds the initial clauses
+        if self.progress.is_none() && self.query.is_none() && self.current_iterations().is_empty() && !self.queue_entries.is_empty() {
+            let queue_entries = mem::take(&mut self.queue_entries);
+            self.saturations.last_mut().unwrap().clauses.update_queue(&queue_entries, 0, |_| Some(0));
+        }
+
         if self.progress.is_none() || self.query.is_none() {
             return None;
         }
@@ -98,32 +296,49 @@ impl SaturationState {
         let progress = self.progress.unwrap();
         let query = self.query.clone().unwrap();
 
-        let new_queue_entries = get_new_queue_entries(&self.last_iteration_queue_entries, &self.queue_entries);
-        self.last_iteration_queue_entries = Vec::new();
-        mem::swap(&mut self.last_iteration_queue_entries, &mut self.queue_entries);
+        let first_iteration = self.iterations.len() == self.current_saturation().first_iteration_index;
+        let saturation = self.saturations.last_mut().unwrap();
+        let is_initial_clause = |canonical: &str| saturation.initial_clauses.iter().any(|initial_clause| initial_clause.canonical == canonical);
+
+        // the first iteration selects an initial clause, so does any iteration selecting a clause printed as initial clause
+        let selected_rule_number = saturation.clauses.select(&query, progress.iteration);
+        let derivation_depth = match selected_rule_number.and_then(|number| saturation.clauses.clauses[&number].depth) {
+            Some(depth) => Some(depth),
+            None if first_iteration || is_initial_clause(&canonicalize(&query)) => Some(0),
+            None => None,
+        };
+
+        // initial clauses still in the queue are only recognized if they were printed, or dumped before the first selection
+        let depth_of = |entry: &QueueEntry| match is_initial_clause(&entry.canonical) {
+            true => Some(0),
+            false => derivation_depth.map(|depth| depth + 1),
+        };
+
+        // without a dump in this iteration, the queue is only known to be unchanged if it is empty
+        let queue_entries = mem::take(&mut self.queue_entries);
+        let new_queue_entries = match queue_entries.is_empty() && progress.in_queue > 0 {
+            true => Vec::new(),
+            false => saturation.clauses.update_queue(&queue_entries, progress.iteration, depth_of),
+        };
 
         let iteration = Iteration {
             progress,
             query,
             new_queue_entries,
+            selected_rule_number,
+            derivation_depth,
             hypothesis_fact_selected: self.hypothesis_fact_selected.clone(),
             conclusion_fact_selected: self.conclusion_fact_selected.clone(),
         };
 
-        // keep aggregated history of selected hypothesis (useful to detect loops)
-        if let Some(previous_iteration) = self.iterations.last() {
-            if previous_iteration.hypothesis_fact_selected.is_some() && previous_iteration.hypothesis_fact_selected == iteration.hypothesis_fact_selected {
-                let last_index = self.hypothesis_selected_fact_history.len() - 1;
-                let (fact, count) = self.hypothesis_selected_fact_history[last_index].clone();
-                self.hypothesis_selected_fact_history[last_index] = (fact.clone(), count + 1);
-            } else if let Some(hypothesis_fact_selected) = iteration.hypothesis_fact_selected.clone() {
-                self.hypothesis_selected_fact_history.push((hypothesis_fact_selected.fact, 1))
-            }
+        let selected_fact = Self::print_selected_fact(&iteration, &self.current_iterations().last());
+        let new_queue_entries = iteration.new_queue_entries.iter().map(|entry| entry.rule.clone()).collect();
+        let mut summary = IterationSummary::new(selected_fact, iteration.query.clone(), new_queue_entries, format!("{}", iteration.progress));
+        if let Some(clause) = selected_rule_number.and_then(|number| self.current_saturation().clauses.clauses.get(&number)) {
+            let waiting_time = clause.waiting_time().unwrap_or(0);
+            summary.add_selected_rule(format!("rule {} for {waiting_time} iterations", clause.entry.number));
         }
 
-        let selected_fact = Self::print_selected_fact(&iteration, &self.iterations.last());
-        let summary = IterationSummary::new(selected_fact, iteration.query.clone(), iteration.new_queue_entries.clone(), format!("{}", iteration.progress));
-
         self.progress = None;
         self.query = None;
         self.hypothesis_fact_selected = None;
@@ -167,41 +382,44 @@ impl SaturationState {
     }
 }
 
-fn get_new_queue_entries(previous_queue: &Vec<String>, current_queue: &[String]) -> Vec<String> {
-    let mut current_queue_threshold = 0;
-    for entry in previous_queue {
-        if current_queue_threshold == current_queue.len() {
-            return Vec::new();
-        }
-
-        if *entry == current_queue[current_queue_threshold] {
-            current_queue_threshold += 1
-        }
-    }
-
-    current_queue[current_queue_threshold..].to_vec()
+#[test]
+fn test_saturations() {
+    let mut saturation_state = SaturationState::new();
+    saturation_state.start_saturation("Query not attacker(s[]) in process 0".to_string());
+    assert_eq!(saturation_state.saturations.len(), 1);
+
+    saturation_state.set_saturation_progress(1, 0, 1, 0);
+    saturation_state.set_query("att2(x_1,x_1)".to_string());
+    saturation_state.complete_iteration();
+    saturation_state.add_result("RESULT not attacker(s[]) is true.".to_string());
+
+    saturation_state.start_saturation("Query not attacker(k[]) in process 1".to_string());
+    saturation_state.set_saturation_progress(1, 0, 1, 0);
+    saturation_state.set_query("att2(x_1,x_1)".to_string());
+    saturation_state.complete_iteration();
+
+    assert_eq!(saturation_state.saturations.len(), 2);
+    assert_eq!(saturation_state.current_iterations().len(), 1);
+    assert_eq!(saturation_state.saturation_of(1).first_iteration_index, 1);
 }
 
 #[test]
-fn test_get_new_queue_entries() {
-    let previous_queue = vec!["a".to_string(), "b".to_string(), "c".to_string()];
-    let current_queue = vec!["a".to_string(), "b".to_string(), "c".to_string(), "d".to_string()];
-    let new_queue_entries = vec!["d".to_string()];
-    assert_eq!(get_new_queue_entries(&previous_queue, &current_queue), new_queue_entries);
-
-    let current_queue = vec!["b".to_string(), "d".to_string()];
-    let new_queue_entries = vec!["d".to_string()];
-    assert_eq!(get_new_queue_entries(&previous_queue, &current_queue), new_queue_entries);
-
-    let current_queue = vec!["d".to_string()];
-    let new_queue_entries = vec!["d".to_string()];
-    assert_eq!(get_new_queue_entries(&previous_queue, &current_queue), new_queue_entries);
-
-    let current_queue = vec![];
-    let new_queue_entries: Vec<String> = vec![];
-    assert_eq!(get_new_queue_entries(&previous_queue, &current_queue), new_queue_entries);
-
-    let previous_queue = vec![];
-    let current_queue = vec!["a".to_string()];
-    assert_eq!(get_new_queue_entries(&previous_queue, &current_queue), current_queue);
+fn test_derivation_depth() {
+    let select = |saturation_state: &mut SaturationState, query: &str, iteration: usize, queue: &[(usize, &str)]| {
+        // a selection starts, as on `LogEvent::SelectionStarted`
+        saturation_state.complete_iteration();
+        saturation_state.set_query(query.to_string());
+        saturation_state.set_saturation_progress(iteration, 0, iteration, queue.len());
+        queue.iter().for_each(|(number, rule)| saturation_state.set_queue_entry(*number, rule.to_string()));
+        saturation_state.complete_iteration();
+        saturation_state.iterations.last().unwrap().derivation_depth
+    };
+
+    // rule 1 was queued before the first selection, rule 2 was derived by the first iteration
+    let mut saturation_state = SaturationState::new();
+    saturation_state.set_queue_entry(0, "att2(x_1,x_1) -> att2(f(x_1),x_1)".to_string());
+    saturation_state.set_queue_entry(1, "att2(x_1,x_1) -> att2(g(x_1),x_1)".to_string());
+    assert_eq!(select(&mut saturation_state, "att2(x_1,x_1) -> att2(f(x_1),x_1)", 1, &[(1, "att2(x_1,x_1) -> att2(g(x_1),x_1)"), (2, "att2(x_1,x_1) -> att2(h(x_1),x_1)")]), Some(0));
+    assert_eq!(select(&mut saturation_state, "att2(x_1,x_1) -> att2(g(x_1),x_1)", 2, &[(2, "att2(x_1,x_1) -> att2(h(x_1),x_1)")]), Some(0));
+    assert_eq!(select(&mut saturation_state, "att2(x_1,x_1) -> att2(h(x_1),x_1)", 3, &[]), Some(1));
 }
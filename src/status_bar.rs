@@ -0,0 +1,165 @@
+use crate::iteration_summary::IterationSummary;
+use crate::printer::{ColorChoice, Printer};
+use crate::saturation_state::{Iteration, SaturationProgress};
+use crate::Cli;
+use std::collections::{HashMap, VecDeque};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const RATE_INTERVAL: Duration = Duration::from_secs(1);
+const SPARKLINE_LENGTH: usize = 60;
+const PREDICATE_WINDOW: usize = 1000;
+const SPARKLINE_CHARACTERS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// fixed footer showing the state of the saturation, redrawn below the findings
+pub struct StatusBar {
+    width: usize,
+    last_draw: Option<Instant>,
+    drawn_lines: usize,
+
+    progress: Option<SaturationProgress>,
+    rate_sample: (Instant, usize),
+    rate: f64,
+    queue_samples: VecDeque<usize>,
+    predicate_window: VecDeque<String>,
+    predicate_counts: HashMap<String, usize>,
+    last_finding: Option<String>,
+}
+
+impl StatusBar {
+    /// the footer is redrawn with escape sequences, hence only shown on a terminal, and not with `--color never`
+    pub fn enabled(cli: &Cli) -> bool {
+        (cli.status || cli.status_only) && cli.color != ColorChoice::Never && std::io::stdout().is_terminal()
+    }
+
+    pub fn new() -> Self {
+        let width = std::env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()).unwrap_or(120);
+
+        StatusBar {
+            width,
+            last_draw: None,
+            drawn_lines: 0,
+
+            progress: None,
+            rate_sample: (Instant::now(), 0),
+            rate: 0.0,
+            queue_samples: VecDeque::new(),
+            predicate_window: VecDeque::new(),
+            predicate_counts: HashMap::new(),
+            last_finding: None,
+        }
+    }
+
+    /// prints iterations with findings above the footer, and redraws the footer if due
+    pub fn print_iteration(&mut self, cli: &Cli, iteration: &Iteration, iteration_summary: &IterationSummary, visible: bool, printer: &Printer) {
+        self.update(iteration, iteration_summary);
+
+        if visible && !cli.status_only && !iteration_summary.is_condensed(cli) {
+            printer.clear_footer(self.drawn_lines);
+            iteration_summary.print(cli, printer);
+            self.draw(printer);
+            return;
+        }
+
+        if self.last_draw.is_none_or(|last_draw| last_draw.elapsed() >= REDRAW_INTERVAL) {
+            printer.clear_footer(self.drawn_lines);
+            self.draw(printer);
+        }
+    }
+
+    /// prints lines which do not belong to an iteration above the footer
+    pub fn print_lines(&mut self, lines: &[String], printer: &Printer) {
+        printer.clear_footer(self.drawn_lines);
+        for line in lines {
+            printer.print(line);
+        }
+        self.draw(printer);
+    }
+
+    /// leaves the last state of the footer in the output
+    pub fn finish(&mut self, printer: &Printer) {
+        printer.clear_footer(self.drawn_lines);
+        self.draw(printer);
+        printer.print_group_separator();
+        self.drawn_lines = 0;
+    }
+
+    fn update(&mut self, iteration: &Iteration, iteration_summary: &IterationSummary) {
+        self.progress = Some(iteration.progress);
+        self.queue_samples.push_back(iteration.progress.in_queue);
+        if self.queue_samples.len() > SPARKLINE_LENGTH {
+            self.queue_samples.pop_front();
+        }
+
+        let elapsed = self.rate_sample.0.elapsed();
+        if elapsed >= RATE_INTERVAL {
+            let iterations = iteration.progress.iteration.saturating_sub(self.rate_sample.1);
+            self.rate = iterations as f64 / elapsed.as_secs_f64();
+            self.rate_sample = (Instant::now(), iteration.progress.iteration);
+        }
+
+        let predicate = iteration.selected_fact().and_then(|f| f.term.as_ref()).and_then(|t| t.predicate()).unwrap_or("?").to_string();
+        *self.predicate_counts.entry(predicate.clone()).or_default() += 1;
+        self.predicate_window.push_back(predicate);
+        if self.predicate_window.len() > PREDICATE_WINDOW {
+            let removed = self.predicate_window.pop_front().unwrap();
+            *self.predicate_counts.get_mut(&removed).unwrap() -= 1;
+        }
+
+        if let Some((severity, header, line)) = iteration_summary.findings().last() {
+            self.last_finding = Some(format!("{:?} at {}: {header}: {line}", severity, iteration.progress.iteration));
+        }
+    }
+
+    fn draw(&mut self, printer: &Printer) {
+        let Some(progress) = self.progress else {
+            return;
+        };
+
+        let base = progress.with_conclusion_selected + progress.with_hypothesis_selected;
+        let status = format!(
+            "iteration {} | queue {} | base {} ({}c, {}h) | {:.1} it/s | dominant {}",
+            progress.iteration,
+            progress.in_queue,
+            base,
+            progress.with_conclusion_selected,
+            progress.with_hypothesis_selected,
+            self.rate,
+            self.describe_dominant_predicate()
+        );
+        let sparkline = format!("queue {} | last finding: {}", sparkline(&self.queue_samples), self.last_finding.as_deref().unwrap_or("none"));
+
+        let lines = vec![truncate(&status, self.width), truncate(&sparkline, self.width)];
+        printer.print_footer(&lines);
+        self.drawn_lines = lines.len();
+        self.last_draw = Some(Instant::now());
+    }
+
+    fn describe_dominant_predicate(&self) -> String {
+        let dominant = self.predicate_counts.iter().max_by_key(|(predicate, count)| (**count, std::cmp::Reverse(predicate.as_str())));
+        match dominant {
+            Some((predicate, count)) if *count > 0 => format!("{predicate} ({}%)", 100 * count / self.predicate_window.len()),
+            _ => "none".to_string(),
+        }
+    }
+}
+
+fn sparkline(samples: &VecDeque<usize>) -> String {
+    let minimum = samples.iter().min().copied().unwrap_or(0);
+    let maximum = samples.iter().max().copied().unwrap_or(0);
+    let range = (maximum - minimum).max(1);
+
+    samples.iter().map(|sample| SPARKLINE_CHARACTERS[(sample - minimum) * (SPARKLINE_CHARACTERS.len() - 1) / range]).collect()
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+#[test]
+fn test_sparkline() {
+    let samples: VecDeque<usize> = vec![10, 20, 30, 80, 10].into();
+    assert_eq!(sparkline(&samples), "▁▂▃█▁");
+    assert_eq!(sparkline(&vec![5, 5].into()), "▁▁");
+}
@@ -0,0 +1,536 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Term {
+    /// function application, tuple constructors excluded; constants have no arguments
+    Function(String, Vec<Term>),
+    /// name with its (session) annotations, like `e_id[!1 = @sid]`
+    Name(String, Vec<(String, Term)>),
+    Tuple(Vec<Term>),
+    Choice(Box<Term>, Box<Term>),
+    Variable(String),
+    Integer(u64),
+}
+
+/// side of a biprocess in equivalence proofs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// predicates of equivalence proofs, with the arguments of the left side followed by those of the right side
+const BI_PREDICATES: [&str; 5] = ["att2", "mess2", "table2", "input2", "output2"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct TermMetrics {
+    pub depth: usize,
+    pub size: usize,
+    pub variables: usize,
+    pub session_names: usize,
+}
+
+/// replication annotations of a fact: session indices of names (`e_id[!1 = @sid]`) and replication index variables (`@1_j_1`)
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SessionAnnotations {
+    /// distinct session indices, i.e. the values of `!N` annotations and the variables starting with `@`
+    pub indices: BTreeSet<String>,
+    /// most replication markers (`!N`) on one path through the fact, counting names nested in the annotations of other names
+    pub replication_depth: usize,
+    /// names with `replication_depth` markers on their path, i.e. created by the innermost replicated process
+    pub innermost_names: BTreeSet<String>,
+}
+
+impl Term {
+    pub fn parse(input: &str) -> Option<Term> {
+        let tokens = tokenize(input);
+        let mut parser = TermParser { tokens, position: 0 };
+        let term = parser.parse_term()?;
+
+        if parser.position != parser.tokens.len() {
+            return None;
+        }
+
+        Some(term)
+    }
+
+    /// predicate name if this term is a fact
+    pub fn predicate(&self) -> Option<&str> {
+        match self {
+            Term::Function(name, _) => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn is_bi_fact(&self) -> bool {
+        match self {
+            Term::Function(name, arguments) => BI_PREDICATES.contains(&name.as_str()) && arguments.len().is_multiple_of(2),
+            _ => false,
+        }
+    }
+
+    /// whether the sides of the biprocess might see the term differently, i.e. it is or contains a bi-fact or `choice`
+    pub fn has_sides(&self) -> bool {
+        self.is_bi_fact() || matches!(self, Term::Choice(_, _)) || self.children().iter().any(|child| child.has_sides())
+    }
+
+    /// the term as seen by one side of the biprocess: `choice[l,r]` becomes `l` or `r`, and bi-facts like `att2(l,r)` become `att(l)` or `att(r)`
+    pub fn project(&self, side: Side) -> Term {
+        let project_all = |terms: &[Term]| terms.iter().map(|t| t.project(side)).collect();
+        match self {
+            Term::Function(name, arguments) if self.is_bi_fact() => {
+                let (left, right) = arguments.split_at(arguments.len() / 2);
+                let arguments = match side {
+                    Side::Left => left,
+                    Side::Right => right,
+                };
+                Term::Function(name.trim_end_matches('2').to_string(), project_all(arguments))
+            }
+            Term::Function(name, arguments) => Term::Function(name.clone(), project_all(arguments)),
+            Term::Name(name, annotations) => Term::Name(name.clone(), annotations.iter().map(|(key, a)| (key.clone(), a.project(side))).collect()),
+            Term::Tuple(elements) => Term::Tuple(project_all(elements)),
+            Term::Choice(left, _) if side == Side::Left => left.project(side),
+            Term::Choice(_, right) => right.project(side),
+            Term::Variable(_) | Term::Integer(_) => self.clone(),
+        }
+    }
+
+    /// maximum nesting of function symbols (variables and literals do not count)
+    pub fn depth(&self) -> usize {
+        match self {
+            Term::Function(_, arguments) | Term::Tuple(arguments) => 1 + arguments.iter().map(|a| a.depth()).max().unwrap_or(0),
+            Term::Name(_, _) => 1,
+            Term::Choice(left, right) => left.depth().max(right.depth()),
+            Term::Variable(_) | Term::Integer(_) => 0,
+        }
+    }
+
+    /// number of nodes in the term, including annotations of names
+    pub fn size(&self) -> usize {
+        match self {
+            Term::Function(_, arguments) | Term::Tuple(arguments) => 1 + arguments.iter().map(|a| a.size()).sum::<usize>(),
+            Term::Name(_, annotations) => 1 + annotations.iter().map(|(_, a)| a.size()).sum::<usize>(),
+            Term::Choice(left, right) => 1 + left.size() + right.size(),
+            Term::Variable(_) | Term::Integer(_) => 1,
+        }
+    }
+
+    pub fn children(&self) -> Vec<&Term> {
+        match self {
+            Term::Function(_, arguments) | Term::Tuple(arguments) => arguments.iter().collect(),
+            Term::Name(_, annotations) => annotations.iter().map(|(_, a)| a).collect(),
+            Term::Choice(left, right) => vec![left, right],
+            Term::Variable(_) | Term::Integer(_) => vec![],
+        }
+    }
+
+    pub fn collect_variables<'a>(&'a self, variables: &mut HashSet<&'a str>) {
+        if let Term::Variable(name) = self {
+            variables.insert(name);
+        }
+
+        for child in self.children() {
+            child.collect_variables(variables);
+        }
+    }
+
+    /// renames variables to `x_1`, `x_2`, ... in order of their first occurrence (shared renaming passed in)
+    pub fn rename_variables(&self, renaming: &mut HashMap<String, String>) -> Term {
+        match self {
+            Term::Function(name, arguments) => Term::Function(name.clone(), arguments.iter().map(|a| a.rename_variables(renaming)).collect()),
+            Term::Name(name, annotations) => Term::Name(name.clone(), annotations.iter().map(|(key, a)| (key.clone(), a.rename_variables(renaming))).collect()),
+            Term::Tuple(elements) => Term::Tuple(elements.iter().map(|e| e.rename_variables(renaming)).collect()),
+            Term::Choice(left, right) => Term::Choice(Box::new(left.rename_variables(renaming)), Box::new(right.rename_variables(renaming))),
+            Term::Variable(name) => {
+                let next_name = format!("x_{}", renaming.len() + 1);
+                Term::Variable(renaming.entry(name.clone()).or_insert(next_name).clone())
+            }
+            Term::Integer(value) => Term::Integer(*value),
+        }
+    }
+
+    /// the term with all variables replaced by `_`, to compare terms by their structure
+    pub fn erase_variables(&self) -> Term {
+        let mut term = self.clone();
+        term.replace_variables("_");
+        term
+    }
+
+    fn replace_variables(&mut self, replacement: &str) {
+        match self {
+            Term::Variable(name) => *name = replacement.to_string(),
+            Term::Function(_, terms) | Term::Tuple(terms) => terms.iter_mut().for_each(|t| t.replace_variables(replacement)),
+            Term::Name(_, annotations) => annotations.iter_mut().for_each(|(_, t)| t.replace_variables(replacement)),
+            Term::Choice(left, right) => {
+                left.replace_variables(replacement);
+                right.replace_variables(replacement);
+            }
+            Term::Integer(_) => {}
+        }
+    }
+
+    /// names created below a replication, i.e. annotated with a session index like `!1`
+    pub fn count_session_names(&self) -> usize {
+        let own = match self {
+            Term::Name(_, annotations) if annotations.iter().any(|(key, _)| key.starts_with('!')) => 1,
+            _ => 0,
+        };
+
+        own + self.children().iter().map(|c| c.count_session_names()).sum::<usize>()
+    }
+}
+
+impl Term {
+    pub fn session_annotations(&self) -> SessionAnnotations {
+        let mut annotations = SessionAnnotations::default();
+        self.collect_session_annotations(0, &mut annotations);
+        annotations
+    }
+
+    fn collect_session_annotations(&self, outer_markers: usize, annotations: &mut SessionAnnotations) {
+        let mut markers = outer_markers;
+        match self {
+            Term::Variable(name) if name.starts_with('@') => {
+                annotations.indices.insert(name.clone());
+            }
+            Term::Name(name, name_annotations) => {
+                for (_, index) in name_annotations.iter().filter(|(key, _)| key.starts_with('!')) {
+                    annotations.indices.insert(index.to_string());
+                    markers += 1;
+                }
+
+                if markers > outer_markers && markers >= annotations.replication_depth {
+                    if markers > annotations.replication_depth {
+                        annotations.replication_depth = markers;
+                        annotations.innermost_names.clear();
+                    }
+                    annotations.innermost_names.insert(name.clone());
+                }
+            }
+            _ => {}
+        }
+
+        for child in self.children() {
+            child.collect_session_annotations(markers, annotations);
+        }
+    }
+}
+
+impl TermMetrics {
+    /// metrics of the arguments of a fact (the predicate itself is not counted)
+    pub fn of_fact(fact: &Term) -> Self {
+        let arguments = match fact {
+            Term::Function(_, arguments) => arguments.iter().collect(),
+            _ => vec![fact],
+        };
+
+        let mut variables = HashSet::new();
+        for argument in arguments.iter() {
+            argument.collect_variables(&mut variables);
+        }
+
+        TermMetrics {
+            depth: arguments.iter().map(|a| a.depth()).max().unwrap_or(0),
+            size: arguments.iter().map(|a| a.size()).sum(),
+            variables: variables.len(),
+            session_names: arguments.iter().map(|a| a.count_session_names()).sum(),
+        }
+    }
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::Left => write!(f, "left"),
+            Side::Right => write!(f, "right"),
+        }
+    }
+}
+
+impl Display for TermMetrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d {}s {}v {}n", self.depth, self.size, self.variables, self.session_names)
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Term::Function(name, arguments) if arguments.is_empty() => write!(f, "{name}"),
+            Term::Function(name, arguments) if arguments.len() == 2 && is_infix(name) => write!(f, "{} {name} {}", arguments[0], arguments[1]),
+            Term::Function(name, arguments) => write!(f, "{name}({})", join(arguments)),
+            Term::Name(name, annotations) => {
+                let annotations: Vec<String> = annotations.iter().map(|(key, value)| format!("{key} = {value}")).collect();
+                write!(f, "{name}[{}]", annotations.join(","))
+            }
+            Term::Tuple(elements) => write!(f, "({})", join(elements)),
+            Term::Choice(left, right) => write!(f, "choice[{left},{right}]"),
+            Term::Variable(name) => write!(f, "{name}"),
+            Term::Integer(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+fn join(terms: &[Term]) -> String {
+    terms.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(",")
+}
+
+/// arithmetic on naturals, and constraints in hypotheses of clauses
+fn is_infix(name: &str) -> bool {
+    matches!(name, "+" | "-" | "<>" | ">=" | ">" | "<=" | "<")
+}
+
+/// ProVerif prints renamed variables with a numeric suffix (`v_8`) and replication indices with an `@` prefix (`@1_j_1`)
+fn is_variable(identifier: &str) -> bool {
+    if identifier.starts_with('@') {
+        return true;
+    }
+
+    match identifier.rsplit_once('_') {
+        Some((prefix, suffix)) => !prefix.is_empty() && !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Equals,
+    Identifier(String),
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut identifier = String::new();
+
+    let mut chars = input.chars().peekable();
+    while let Some(char) = chars.next() {
+        let token = match char {
+            '(' => Some(Token::Open),
+            ')' => Some(Token::Close),
+            '[' => Some(Token::OpenBracket),
+            ']' => Some(Token::CloseBracket),
+            ',' => Some(Token::Comma),
+            '=' => Some(Token::Equals),
+            // comparisons are operators even without surrounding spaces, `<=`, `>=` and `<>` being one token
+            '<' | '>' => {
+                let mut operator = char.to_string();
+                if let Some(next) = chars.next_if(|next| *next == '=' || (char == '<' && *next == '>')) {
+                    operator.push(next);
+                }
+                Some(Token::Identifier(operator))
+            }
+            _ if char.is_whitespace() => None,
+            _ => {
+                identifier.push(char);
+                continue;
+            }
+        };
+
+        if !identifier.is_empty() {
+            tokens.push(Token::Identifier(identifier.clone()));
+            identifier.clear();
+        }
+
+        if let Some(token) = token {
+            tokens.push(token);
+        }
+    }
+
+    if !identifier.is_empty() {
+        tokens.push(Token::Identifier(identifier));
+    }
+
+    tokens
+}
+
+struct TermParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl TermParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        if *self.next()? == token {
+            return Some(());
+        }
+
+        None
+    }
+
+    fn parse_term(&mut self) -> Option<Term> {
+        let term = self.parse_primary()?;
+
+        if let Some(Token::Identifier(operator)) = self.peek() {
+            if is_infix(operator) {
+                let operator = operator.clone();
+                self.position += 1;
+                let right = self.parse_term()?;
+                return Some(Term::Function(operator, vec![term, right]));
+            }
+        }
+
+        Some(term)
+    }
+
+    fn parse_primary(&mut self) -> Option<Term> {
+        let identifier = match self.next()? {
+            Token::Open => {
+                let elements = self.parse_list(Token::Close)?;
+                return Some(Term::Tuple(elements));
+            }
+            Token::Identifier(identifier) => identifier.clone(),
+            _ => return None,
+        };
+
+        match self.peek() {
+            Some(Token::Open) => {
+                self.position += 1;
+                let arguments = self.parse_list(Token::Close)?;
+                Some(Term::Function(identifier, arguments))
+            }
+            Some(Token::OpenBracket) if identifier == "choice" || identifier == "diff" => {
+                self.position += 1;
+                let mut elements = self.parse_list(Token::CloseBracket)?;
+                if elements.len() != 2 {
+                    return None;
+                }
+
+                let right = elements.pop().unwrap();
+                let left = elements.pop().unwrap();
+                Some(Term::Choice(Box::new(left), Box::new(right)))
+            }
+            Some(Token::OpenBracket) => {
+                self.position += 1;
+                let annotations = self.parse_annotations()?;
+                Some(Term::Name(identifier, annotations))
+            }
+            _ => {
+                if let Ok(value) = identifier.parse::<u64>() {
+                    return Some(Term::Integer(value));
+                }
+
+                if is_variable(&identifier) {
+                    return Some(Term::Variable(identifier));
+                }
+
+                Some(Term::Function(identifier, vec![]))
+            }
+        }
+    }
+
+    fn parse_list(&mut self, end: Token) -> Option<Vec<Term>> {
+        let mut elements = Vec::new();
+        if *self.peek()? == end {
+            self.position += 1;
+            return Some(elements);
+        }
+
+        loop {
+            elements.push(self.parse_term()?);
+
+            match self.next()? {
+                Token::Comma => continue,
+                token if *token == end => return Some(elements),
+                _ => return None,
+            }
+        }
+    }
+
+    fn parse_annotations(&mut self) -> Option<Vec<(String, Term)>> {
+        let mut annotations = Vec::new();
+        if *self.peek()? == Token::CloseBracket {
+            self.position += 1;
+            return Some(annotations);
+        }
+
+        loop {
+            let key = match self.next()? {
+                Token::Identifier(key) => key.clone(),
+                _ => return None,
+            };
+            self.expect(Token::Equals)?;
+            annotations.push((key, self.parse_term()?));
+
+            match self.next()? {
+                Token::Comma => continue,
+                Token::CloseBracket => return Some(annotations),
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_fact() {
+    let fact = Term::parse("mess2(cell_BB(e_id[!1 = @sid],6),v_8,cell_BB(e_id[!1 = @sid],6),(@1_j_1,@1_k_1,@1_d_1))").unwrap();
+    assert_eq!(fact.predicate(), Some("mess2"));
+    assert_eq!(fact.to_string(), "mess2(cell_BB(e_id[!1 = @sid],6),v_8,cell_BB(e_id[!1 = @sid],6),(@1_j_1,@1_k_1,@1_d_1))");
+
+    let fact = Term::parse("att2(choice[k[],x_1 + 1],c)").unwrap();
+    let expected = Term::Function(
+        "att2".to_string(),
+        vec![
+            Term::Choice(Box::new(Term::Name("k".to_string(), vec![])), Box::new(Term::Function("+".to_string(), vec![Term::Variable("x_1".to_string()), Term::Integer(1)]))),
+            Term::Function("c".to_string(), vec![]),
+        ],
+    );
+    assert_eq!(fact, expected);
+
+    let constraint = Term::parse("x_1>=2").unwrap();
+    assert_eq!(constraint, Term::Function(">=".to_string(), vec![Term::Variable("x_1".to_string()), Term::Integer(2)]));
+    assert_eq!(constraint.to_string(), "x_1 >= 2");
+    assert_eq!(Term::parse("x_1 <= y_1").unwrap().to_string(), "x_1 <= y_1");
+    assert_eq!(Term::parse("x_1 <> y_1").unwrap().to_string(), "x_1 <> y_1");
+    assert_eq!(Term::parse("x_1 < y_1").unwrap().to_string(), "x_1 < y_1");
+
+    assert_eq!(Term::parse("mess2(a,b"), None);
+    assert_eq!(Term::parse("mess2(a,b))"), None);
+}
+
+#[test]
+fn test_term_metrics() {
+    let fact = Term::parse("mess2(cell_BB(e_id[!1 = @sid],6),v_8,cell_BB(e_id[!1 = @sid],6),(@1_j_1,@1_k_1,@1_d_1))").unwrap();
+    let metrics = TermMetrics::of_fact(&fact);
+    assert_eq!(metrics, TermMetrics { depth: 2, size: 13, variables: 5, session_names: 2 });
+}
+
+#[test]
+fn test_session_annotations() {
+    let fact = Term::parse("mess2(cell_BB(e_id[!1 = @sid],6),v_8,k[!1 = @sid_1,x = n[!1 = @sid]],(@1_j_1,@1_k_1))").unwrap();
+    let annotations = fact.session_annotations();
+    assert_eq!(annotations.indices.iter().map(String::as_str).collect::<Vec<_>>(), vec!["@1_j_1", "@1_k_1", "@sid", "@sid_1"]);
+    assert_eq!(annotations.replication_depth, 2);
+    assert_eq!(annotations.innermost_names.iter().map(String::as_str).collect::<Vec<_>>(), vec!["n"]);
+
+    assert_eq!(Term::parse("att2(x_1,c[])").unwrap().session_annotations(), SessionAnnotations::default());
+}
+
+#[test]
+fn test_project() {
+    let fact = Term::parse("mess2(c[],choice[k[],f(x_1)],c[],(x_1,choice[a,b]))").unwrap();
+    assert!(fact.is_bi_fact());
+    assert_eq!(fact.project(Side::Left).to_string(), "mess(c[],k[])");
+    assert_eq!(fact.project(Side::Right).to_string(), "mess(c[],(x_1,b))");
+
+    let fact = Term::parse("att2(choice[k[],x_1],x_2)").unwrap();
+    assert_eq!(fact.project(Side::Right).to_string(), "att(x_2)");
+    assert!(!Term::parse("bad").unwrap().is_bi_fact());
+
+    assert!(Term::parse("f(x_1,choice[a[],b[]])").unwrap().has_sides());
+    assert!(!Term::parse("mess(c[],f(x_1))").unwrap().has_sides());
+}
@@ -0,0 +1,45 @@
+use crate::iteration_summary::IterationSummary;
+use crate::term::TermMetrics;
+use crate::Cli;
+
+pub struct TermMetricsChecker {
+    thresholds: TermMetrics,
+    maximum: TermMetrics,
+}
+
+impl TermMetricsChecker {
+    pub fn new(cli: &Cli) -> Self {
+        let thresholds = TermMetrics {
+            depth: cli.max_term_depth,
+            size: cli.max_term_size,
+            variables: cli.max_term_variables,
+            session_names: cli.max_session_names,
+        };
+
+        TermMetricsChecker { thresholds, maximum: TermMetrics::default() }
+    }
+
+    pub fn check(&mut self, metrics: &TermMetrics, printer: &mut IterationSummary) {
+        // only report new maxima above the threshold (avoids repeating the same finding for every similar fact)
+        Self::check_metric("depth", metrics.depth, self.thresholds.depth, &mut self.maximum.depth, printer);
+        Self::check_metric("size", metrics.size, self.thresholds.size, &mut self.maximum.size, printer);
+        Self::check_metric("variables", metrics.variables, self.thresholds.variables, &mut self.maximum.variables, printer);
+        Self::check_metric("session names", metrics.session_names, self.thresholds.session_names, &mut self.maximum.session_names, printer);
+    }
+
+    fn check_metric(name: &str, value: usize, threshold: usize, maximum: &mut usize, printer: &mut IterationSummary) {
+        if value <= *maximum {
+            return;
+        }
+        *maximum = value;
+
+        if value > 2 * threshold {
+            printer.add_error("TermMetrics".to_string(), format!("{name} {value} exceeds {threshold} by far"));
+            return;
+        }
+
+        if value > threshold {
+            printer.add_warning("TermMetrics".to_string(), format!("{name} {value} exceeds {threshold}"));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::clause::canonicalize;
+use crate::clause_tracker::ClauseTracker;
+use crate::rule_explainer::{Link, QueryExplainer};
+use crate::saturation_state::{InitialClause, Iteration, Saturation};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// longest derivation written as witness, the oldest steps are left out
+const MAX_ANCESTRY_STEPS: usize = 100;
+
+/// the iterations forming the loop a finding is about
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum LoopSpan {
+    /// one period of a cycle, from the given iteration up to the current one (excluded)
+    Period(usize),
+    /// the derivation of the current iteration, for clauses growing along a chain
+    Ancestry,
+}
+
+/// a finding which comes with a loop, to be written as witness
+pub struct LoopFinding {
+    pub header: String,
+    pub description: String,
+    pub span: LoopSpan,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct WitnessStep {
+    iteration: usize,
+    selected_fact: String,
+    clause: String,
+    link: String,
+}
+
+/// writes one period of each loop as a sequence of canonical clauses, each loop only once
+pub struct WitnessWriter {
+    writer: BufWriter<File>,
+    written: HashSet<Vec<(String, String)>>,
+}
+
+impl WitnessWriter {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(WitnessWriter {
+            writer: BufWriter::new(File::create(path)?),
+            written: HashSet::new(),
+        })
+    }
+
+    /// `iterations` are the iterations of the `saturation`, the last one being the current iteration
+    pub fn write(&mut self, loop_finding: &LoopFinding, iterations: &[Iteration], saturation: &Saturation) -> std::io::Result<()> {
+        let Some(current_iteration) = iterations.last() else {
+            return Ok(());
+        };
+
+        let steps = match loop_finding.span {
+            LoopSpan::Period(first_iteration) => period_steps(iterations, first_iteration, &saturation.clauses),
+            LoopSpan::Ancestry => ancestry_steps(iterations, &saturation.initial_clauses),
+        };
+        if steps.is_empty() {
+            return Ok(());
+        }
+
+        // the same loop is found again when it repeats, possibly starting at another step
+        let mut key: Vec<(String, String)> = steps.iter().map(|step| (step.selected_fact.clone(), step.clause.clone())).collect();
+        if let LoopSpan::Period(_) = loop_finding.span {
+            key = minimal_rotation(key);
+        }
+        if !self.written.insert(key) {
+            return Ok(());
+        }
+
+        writeln!(self.writer, "Witness {}: {} at iteration {}: {}", self.written.len(), loop_finding.header, current_iteration.progress.iteration, loop_finding.description)?;
+        for (index, step) in steps.iter().enumerate() {
+            writeln!(self.writer, "  step {}, iteration {}, {}", index + 1, step.iteration, step.link)?;
+            writeln!(self.writer, "    selected: {}", step.selected_fact)?;
+            writeln!(self.writer, "    clause: {}", step.clause)?;
+        }
+        writeln!(self.writer)
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn period_steps(iterations: &[Iteration], first_iteration: usize, clauses: &ClauseTracker) -> Vec<WitnessStep> {
+    let earlier_iterations = &iterations[..iterations.len().saturating_sub(1)];
+    let start = earlier_iterations.iter().rposition(|iteration| iteration.progress.iteration < first_iteration).map_or(0, |position| position + 1);
+    let period = &earlier_iterations[start..];
+
+    period
+        .iter()
+        .map(|iteration| {
+            let enqueued_iteration = iteration.selected_rule_number.and_then(|number| clauses.clauses.get(&number)).map(|clause| clause.enqueued_iteration);
+            let link = match enqueued_iteration {
+                Some(enqueued_iteration) => match period.iter().position(|step| step.progress.iteration == enqueued_iteration) {
+                    Some(position) => format!("queued by step {}", position + 1),
+                    None => format!("queued by iteration {enqueued_iteration}"),
+                },
+                None => "queued by an unknown iteration".to_string(),
+            };
+            step(iteration, link)
+        })
+        .collect()
+}
+
+fn ancestry_steps(iterations: &[Iteration], initial_clauses: &[InitialClause]) -> Vec<WitnessStep> {
+    let ancestry = QueryExplainer::get_ancestry(iterations, initial_clauses);
+    let ancestry = &ancestry[..ancestry.len().min(MAX_ANCESTRY_STEPS)];
+
+    // the oldest ancestor first; each ancestor queued the clause selected by the next one
+    let mut steps = Vec::new();
+    for (position, ancestor) in ancestry.iter().enumerate().rev() {
+        let link = match ancestry.get(position + 1) {
+            Some(_) if matches!(ancestor.link, Link::Exact | Link::Selected) => format!("queued by step {}", steps.len()),
+            Some(_) => format!("queued by step {} ({}, confidence {:.1})", steps.len(), ancestor.link, ancestor.link.confidence()),
+            None => "first known ancestor".to_string(),
+        };
+        steps.push(step(&ancestor.iteration, link));
+    }
+
+    steps
+}
+
+fn step(iteration: &Iteration, link: String) -> WitnessStep {
+    let selected_fact = match (&iteration.hypothesis_fact_selected, &iteration.conclusion_fact_selected) {
+        (Some(selected_fact), _) => format!("hypothesis {}", canonicalize(&selected_fact.fact)),
+        (None, Some(selected_fact)) => format!("conclusion {}", canonicalize(&selected_fact.fact)),
+        (None, None) => "unknown".to_string(),
+    };
+
+    WitnessStep {
+        iteration: iteration.progress.iteration,
+        selected_fact,
+        clause: canonicalize(&iteration.query),
+        link,
+    }
+}
+
+fn minimal_rotation<T: Ord + Clone>(sequence: Vec<T>) -> Vec<T> {
+    (0..sequence.len()).map(|start| [&sequence[start..], &sequence[..start]].concat()).min().unwrap_or(sequence)
+}
+
+#[test]
+fn test_witness() {
+    let directory = std::env::temp_dir();
+    let path = directory.join(format!("proverif_terminator_test_witness_{}.txt", std::process::id()));
+    let mut writer = WitnessWriter::create(&path).unwrap();
+    let saturation_state = crate::saturation_state::SaturationState::new();
+    let saturation = saturation_state.current_saturation();
+
+    // the cycle f, g, f, g, f: the second report of the same cycle starts at g, and is not written again
+    let iterations: Vec<Iteration> = ["f", "g", "f", "g", "f"]
+        .iter()
+        .enumerate()
+        .map(|(index, function)| {
+            let fact = format!("att2({function}(x_{index}),y_{index})");
+            Iteration::for_test(index + 1, &format!("{fact} -> att2(x_{index},y_{index})"), Some(&fact))
+        })
+        .collect();
+    let loop_finding = |first_iteration| LoopFinding {
+        header: "Hypothesis cycle".to_string(),
+        description: "size 2, repeat 2".to_string(),
+        span: LoopSpan::Period(first_iteration),
+    };
+    writer.write(&loop_finding(3), &iterations[..5], saturation).unwrap();
+    writer.write(&loop_finding(2), &iterations[..4], saturation).unwrap();
+    writer.flush().unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        content,
+        "Witness 1: Hypothesis cycle at iteration 5: size 2, repeat 2\n\
+        \x20 step 1, iteration 3, queued by an unknown iteration\n\
+        \x20   selected: hypothesis att2(f(x_1),x_2)\n\
+        \x20   clause: att2(f(x_1),x_2) -> att2(x_1,x_2)\n\
+        \x20 step 2, iteration 4, queued by an unknown iteration\n\
+        \x20   selected: hypothesis att2(g(x_1),x_2)\n\
+        \x20   clause: att2(g(x_1),x_2) -> att2(x_1,x_2)\n\n"
+    );
+}
@@ -0,0 +1,97 @@
+//! Runs the whole pipeline on the logs in `tests/corpus`, and compares the output with the golden files next to them.
+//!
+//! The logs are written by hand in the format of `proverif` with `set verboseRules = true` (selected fact, selected clause, progress
+//! line and queue dump per iteration), as ProVerif is not available where the tests run; each one reproduces a pattern of a real run.
+//!
+//! Every `<name>.log` is piped into the binary, with the arguments in `<name>.args` (whitespace separated) if present, else with
+//! `--color never --detect-all`. The output is compared with `<name>.expected`. In the arguments, `{output}` is replaced by a
+//! path in a temporary directory, so `--html-report {output}.html` writes `{output}.html`, which is compared with
+//! `<name>.html.expected`, and likewise for any other output file. Run with `UPDATE_GOLDEN=1` to write the golden files after an
+//! intended change of the output, and review their diff.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const DEFAULT_ARGUMENTS: &str = "--color never --detect-all";
+
+/// the output, and the output files by the part of their name after `{output}`
+fn run(log: &Path, output_directory: &Path) -> (String, Vec<(String, String)>) {
+    let arguments = match fs::read_to_string(log.with_extension("args")) {
+        Ok(arguments) => arguments,
+        Err(_) => DEFAULT_ARGUMENTS.to_string(),
+    };
+    let output_prefix = output_directory.join("output");
+    let arguments: Vec<String> = arguments.split_whitespace().map(|argument| argument.replace("{output}", &output_prefix.to_string_lossy())).collect();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_proverif_terminator")).args(&arguments).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(&fs::read(log).unwrap()).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let mut result = String::from_utf8_lossy(&output.stdout).into_owned();
+    result.push_str(&String::from_utf8_lossy(&output.stderr));
+    if let Some(code) = output.status.code().filter(|code| *code != 0) {
+        result.push_str(&format!("exit code {code}\n"));
+    }
+
+    let mut output_files: Vec<(String, String)> = fs::read_dir(output_directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| {
+            let suffix = path.file_name().unwrap().to_string_lossy().trim_start_matches("output").to_string();
+            let content = String::from_utf8_lossy(&fs::read(&path).unwrap()).into_owned();
+            fs::remove_file(&path).unwrap();
+            (suffix, content)
+        })
+        .collect();
+    output_files.sort();
+
+    (result, output_files)
+}
+
+/// first differing line, to find the change without an external diff
+fn describe_difference(expected: &str, actual: &str) -> String {
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = actual.lines().collect();
+    let line = (0..expected_lines.len().max(actual_lines.len())).find(|&index| expected_lines.get(index) != actual_lines.get(index)).unwrap_or(0);
+
+    format!("line {}: expected {:?}, got {:?}", line + 1, expected_lines.get(line).unwrap_or(&"<end>"), actual_lines.get(line).unwrap_or(&"<end>"))
+}
+
+#[test]
+fn test_golden_outputs() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut logs: Vec<PathBuf> = fs::read_dir(&corpus).unwrap().map(|entry| entry.unwrap().path()).filter(|path| path.extension().is_some_and(|extension| extension == "log")).collect();
+    logs.sort();
+    assert!(!logs.is_empty(), "no logs in {}", corpus.display());
+
+    let output_directory = std::env::temp_dir().join(format!("proverif_terminator_golden_{}", std::process::id()));
+    fs::create_dir_all(&output_directory).unwrap();
+
+    let mut failures = Vec::new();
+    for log in logs {
+        let (output, output_files) = run(&log, &output_directory);
+        let outputs = [(log.with_extension("expected"), output)]
+            .into_iter()
+            .chain(output_files.into_iter().map(|(suffix, content)| (log.with_extension(format!("{}.expected", suffix.trim_start_matches('.'))), content)));
+
+        for (golden, actual) in outputs {
+            if update {
+                fs::write(&golden, &actual).unwrap();
+                continue;
+            }
+
+            match fs::read_to_string(&golden) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => failures.push(format!("{}: {}", golden.display(), describe_difference(&expected, &actual))),
+                Err(error) => failures.push(format!("{}: could not read {}: {error}", log.display(), golden.display())),
+            }
+        }
+    }
+    fs::remove_dir(&output_directory).unwrap();
+
+    assert!(failures.is_empty(), "output differs from the golden files (run with UPDATE_GOLDEN=1 to update them):\n{}", failures.join("\n"));
+}